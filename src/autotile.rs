@@ -0,0 +1,117 @@
+use crate::{fundamentals::*, maptools::*, utils::*};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// bitmask bits, set when the neighbor in that direction is a wall
+pub const NORTH: u8 = 1;
+pub const EAST: u8 = 2;
+pub const SOUTH: u8 = 4;
+pub const WEST: u8 = 8;
+pub const NORTH_EAST: u8 = 16;
+pub const SOUTH_EAST: u8 = 32;
+pub const SOUTH_WEST: u8 = 64;
+pub const NORTH_WEST: u8 = 128;
+
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq)]
+pub enum Neighborhood {
+    Four,
+    Eight,
+}
+
+fn is_wall(m: &Map, x: i32, y: i32) -> bool {
+    // everything outside the map counts as wall, so edges don't get rims
+    !in_bounds(x, y) || m[y as usize][x as usize] == TileType::Wall as i32
+}
+
+/// wall_bitmask computes the neighbor bitmask of a wall tile.
+/// Diagonal bits are only set if both adjacent orthogonal neighbors are walls too,
+/// which reduces the 256 possible 8-neighbor masks to the 47 visually distinct ones.
+pub fn wall_bitmask(m: &Map, x: i32, y: i32, neighborhood: Neighborhood) -> u8 {
+    let mut mask = 0;
+    for (bit, dx, dy) in [(NORTH, 0, -1), (EAST, 1, 0), (SOUTH, 0, 1), (WEST, -1, 0)] {
+        if is_wall(m, x + dx, y + dy) {
+            mask |= bit;
+        }
+    }
+    if neighborhood == Neighborhood::Eight {
+        for (bit, dx, dy, sides) in [
+            (NORTH_EAST, 1, -1, NORTH | EAST),
+            (SOUTH_EAST, 1, 1, SOUTH | EAST),
+            (SOUTH_WEST, -1, 1, SOUTH | WEST),
+            (NORTH_WEST, -1, -1, NORTH | WEST),
+        ] {
+            if mask & sides == sides && is_wall(m, x + dx, y + dy) {
+                mask |= bit;
+            }
+        }
+    }
+    mask
+}
+
+/// AutotileRules maps wall bitmasks to tile variant names (edges, corners, pillars, ...)
+#[derive(Clone, Debug, Deserialize)]
+pub struct AutotileRules {
+    pub neighborhood: Neighborhood,
+    /// variant used for bitmasks without a rule
+    pub fallback: String,
+    /// variant for walls with floor directly south, the wall top seen in a 3/4 view,
+    /// it takes precedence over the bitmask rules
+    #[serde(default)]
+    pub wall_top: Option<String>,
+    pub rules: HashMap<u8, String>,
+}
+
+impl AutotileRules {
+    pub fn from_ron(s: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(s)
+    }
+
+    /// variant looks up the rule for the exact mask first, then for its orthogonal bits only,
+    /// so 8-neighbor rule sets only need to list the masks where diagonals make a difference
+    pub fn variant(&self, mask: u8) -> &str {
+        if let Some(wall_top) = self.wall_top.as_ref().filter(|_| mask & SOUTH == 0) {
+            return wall_top;
+        }
+        self.rules
+            .get(&mask)
+            .or_else(|| self.rules.get(&(mask & (NORTH | EAST | SOUTH | WEST))))
+            .unwrap_or(&self.fallback)
+    }
+}
+
+/// autotile_map selects a variant for every wall tile, non-wall tiles get None
+pub fn autotile_map<'a>(m: &Map, rules: &'a AutotileRules) -> Vec<Vec<Option<&'a str>>> {
+    (0..ROWS)
+        .map(|y| {
+            (0..COLS)
+                .map(|x| match is_wall(m, x, y) {
+                    true => Some(rules.variant(wall_bitmask(m, x, y, rules.neighborhood))),
+                    false => None,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// autotiled_csv exports the map with wall variant names as comma separated rows,
+/// for use with external level editors & engines. Walls with floor directly south are
+/// named after the wall_top rule, if the rule set has one.
+pub fn autotiled_csv(m: &Map, rules: &AutotileRules) -> String {
+    let tiled = autotile_map(m, rules);
+    let mut csv = String::new();
+    for (y, row) in tiled.iter().enumerate() {
+        let names: Vec<&str> = row
+            .iter()
+            .enumerate()
+            .map(|(x, variant)| match variant {
+                Some(v) => v,
+                None if m[y][x] == TileType::Floor as i32 => "floor",
+                None if m[y][x] == TileType::Door as i32 => "door",
+                None => "hero",
+            })
+            .collect();
+        csv.push_str(&names.join(","));
+        csv.push('\n');
+    }
+    csv
+}
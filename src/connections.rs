@@ -0,0 +1,171 @@
+use crate::utils::*;
+use serde::Serialize;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum ConnectionStrategy {
+    /// every room to the one placed before it
+    Chain,
+    /// the Delaunay triangulation of the room centers, lots of loops
+    Delaunay,
+    /// the minimum spanning tree, plus a share of the other Delaunay edges as loops
+    SpanningTree,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct ConnectionParams {
+    pub strategy: ConnectionStrategy,
+    /// percentage (0-100) of the Delaunay edges outside the spanning tree that are added back
+    pub extra_edges: i32,
+}
+
+impl Default for ConnectionParams {
+    fn default() -> Self {
+        Self {
+            strategy: ConnectionStrategy::Chain,
+            extra_edges: 15,
+        }
+    }
+}
+
+fn dist2(a: (i32, i32), b: (i32, i32)) -> i64 {
+    let (dx, dy) = ((a.0 - b.0) as i64, (a.1 - b.1) as i64);
+    dx * dx + dy * dy
+}
+
+/// ordered stores an edge with the lower index first, so edges compare regardless of direction
+fn ordered(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+/// circumcircle_contains tells if p is inside the circumcircle of the triangle (a, b, c),
+/// degenerate triangles count as containing everything so they get replaced
+fn circumcircle_contains(a: (f64, f64), b: (f64, f64), c: (f64, f64), p: (f64, f64)) -> bool {
+    let d = 2.0 * (a.0 * (b.1 - c.1) + b.0 * (c.1 - a.1) + c.0 * (a.1 - b.1));
+    if d.abs() < 1e-9 {
+        return true;
+    }
+    let (a2, b2, c2) = (
+        a.0 * a.0 + a.1 * a.1,
+        b.0 * b.0 + b.1 * b.1,
+        c.0 * c.0 + c.1 * c.1,
+    );
+    let ux = (a2 * (b.1 - c.1) + b2 * (c.1 - a.1) + c2 * (a.1 - b.1)) / d;
+    let uy = (a2 * (c.0 - b.0) + b2 * (a.0 - c.0) + c2 * (b.0 - a.0)) / d;
+    let r2 = (a.0 - ux).powi(2) + (a.1 - uy).powi(2);
+    (p.0 - ux).powi(2) + (p.1 - uy).powi(2) < r2
+}
+
+/// delaunay_edges triangulates the points with the Bowyer-Watson algorithm.
+/// Duplicate points are skipped, connect them through the spanning tree.
+pub fn delaunay_edges(points: &[(i32, i32)]) -> Vec<(usize, usize)> {
+    let n = points.len();
+    // vertices n.. are the corners of a triangle enclosing all points
+    let mut verts: Vec<(f64, f64)> = points.iter().map(|&(x, y)| (x as f64, y as f64)).collect();
+    verts.extend([(-1.0e5, -1.0e5), (1.0e5, -1.0e5), (0.0, 1.0e5)]);
+    let mut triangles: Vec<[usize; 3]> = vec![[n, n + 1, n + 2]];
+
+    for i in 0..n {
+        if points[..i].contains(&points[i]) {
+            continue;
+        }
+        let p = verts[i];
+        let (bad, good): (Vec<[usize; 3]>, Vec<[usize; 3]>) = triangles
+            .into_iter()
+            .partition(|t| circumcircle_contains(verts[t[0]], verts[t[1]], verts[t[2]], p));
+        triangles = good;
+
+        // the hole left by the bad triangles is bounded by their edges that aren't shared
+        let edges: Vec<(usize, usize)> = bad
+            .iter()
+            .flat_map(|t| {
+                [
+                    ordered(t[0], t[1]),
+                    ordered(t[1], t[2]),
+                    ordered(t[0], t[2]),
+                ]
+            })
+            .collect();
+        for &e in &edges {
+            if edges.iter().filter(|&&other| other == e).count() == 1 {
+                triangles.push([e.0, e.1, i]);
+            }
+        }
+    }
+
+    let mut result: Vec<(usize, usize)> = Vec::new();
+    for t in triangles.iter().filter(|t| t.iter().all(|&v| v < n)) {
+        for e in [
+            ordered(t[0], t[1]),
+            ordered(t[1], t[2]),
+            ordered(t[0], t[2]),
+        ] {
+            if !result.contains(&e) {
+                result.push(e);
+            }
+        }
+    }
+    result
+}
+
+/// spanning_tree_edges returns the minimum spanning tree of the points (Prim's algorithm)
+pub fn spanning_tree_edges(points: &[(i32, i32)]) -> Vec<(usize, usize)> {
+    let n = points.len();
+    if n == 0 {
+        return Vec::new();
+    }
+    let mut in_tree = vec![false; n];
+    // closest tree vertex & its distance, for every vertex not in the tree yet
+    let mut closest: Vec<(usize, i64)> = (0..n).map(|i| (0, dist2(points[0], points[i]))).collect();
+    in_tree[0] = true;
+    let mut edges: Vec<(usize, usize)> = Vec::with_capacity(n - 1);
+
+    for _ in 1..n {
+        let next = (0..n)
+            .filter(|&i| !in_tree[i])
+            .min_by_key(|&i| closest[i].1)
+            .unwrap();
+        in_tree[next] = true;
+        edges.push(ordered(closest[next].0, next));
+        for i in 0..n {
+            let d = dist2(points[next], points[i]);
+            if !in_tree[i] && d < closest[i].1 {
+                closest[i] = (next, d);
+            }
+        }
+    }
+    edges
+}
+
+/// plan_connections picks which pairs of points (room centers) to connect with corridors,
+/// returned as index pairs into points
+pub fn plan_connections(points: &[(i32, i32)], params: &ConnectionParams) -> Vec<(usize, usize)> {
+    match params.strategy {
+        ConnectionStrategy::Chain => (1..points.len()).map(|i| (i - 1, i)).collect(),
+        ConnectionStrategy::Delaunay => {
+            // the spanning tree keeps the map connected when the triangulation degenerates,
+            // e.g. for points on a line
+            let mut edges = delaunay_edges(points);
+            for e in spanning_tree_edges(points) {
+                if !edges.contains(&e) {
+                    edges.push(e);
+                }
+            }
+            edges
+        }
+        ConnectionStrategy::SpanningTree => {
+            let mut edges = spanning_tree_edges(points);
+            let mut extra: Vec<(usize, usize)> = delaunay_edges(points)
+                .into_iter()
+                .filter(|e| !edges.contains(e))
+                .collect();
+            let count = (extra.len() as i32 * params.extra_edges.clamp(0, 100) + 50) / 100;
+            // partial Fisher-Yates shuffle to pick the extra edges
+            for i in 0..count as usize {
+                let j = randr(i as i32..extra.len() as i32) as usize;
+                extra.swap(i, j);
+            }
+            edges.extend(extra.into_iter().take(count as usize));
+            edges
+        }
+    }
+}
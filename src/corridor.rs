@@ -0,0 +1,218 @@
+use crate::{fundamentals::*, maptools::*, utils::*};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// chance (0-100) for a drunken corridor step to head towards the target
+const DRUNKEN_BIAS: i32 = 60;
+/// A* step costs, digging through walls is cheap & cutting through existing floor is not,
+/// so routed corridors go around rooms instead of through them
+const ASTAR_WALL_COST: i32 = 1;
+const ASTAR_ROOM_SIDE_COST: i32 = 4;
+const ASTAR_FLOOR_COST: i32 = 12;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum CorridorStyle {
+    /// horizontal & vertical legs, turning at a random point in between
+    LShaped,
+    /// the straight line between both ends, with a jog wherever it steps diagonally
+    Straight,
+    /// a random walk that is biased towards the target
+    Drunken,
+    /// shortest path that avoids existing floor where it can
+    AStar,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct CorridorParams {
+    pub style: CorridorStyle,
+    pub width: i32,
+}
+
+impl Default for CorridorParams {
+    fn default() -> Self {
+        Self {
+            style: CorridorStyle::LShaped,
+            width: 1,
+        }
+    }
+}
+
+/// inside keeps corridors off the map border, so maps stay enclosed by walls
+fn inside(x: i32, y: i32) -> bool {
+    (1..COLS - 1).contains(&x) && (1..ROWS - 1).contains(&y)
+}
+
+fn l_shaped(from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+    let (x1, y1) = from;
+    let (x2, y2) = to;
+    let horz = |xa: i32, xb: i32, y: i32| {
+        let step = if xb >= xa { 1 } else { -1 };
+        (0..=(xb - xa).abs()).map(move |i| (xa + i * step, y))
+    };
+    let vert = |ya: i32, yb: i32, x: i32| {
+        let step = if yb >= ya { 1 } else { -1 };
+        (0..=(yb - ya).abs()).map(move |i| (x, ya + i * step))
+    };
+    // the elbow may lie anywhere in between, at either end the corridor is a plain L
+    if randr(0..2) == 0 {
+        let xm = randr(x1.min(x2)..x1.max(x2) + 1);
+        horz(x1, xm, y1)
+            .chain(vert(y1, y2, xm).skip(1))
+            .chain(horz(xm, x2, y2).skip(1))
+            .collect()
+    } else {
+        let ym = randr(y1.min(y2)..y1.max(y2) + 1);
+        vert(y1, ym, x1)
+            .chain(horz(x1, x2, ym).skip(1))
+            .chain(vert(ym, y2, x2).skip(1))
+            .collect()
+    }
+}
+
+fn straight(from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+    let mut path: Vec<(i32, i32)> = Vec::new();
+    for (x, y) in line(from.0, from.1, to.0, to.1) {
+        if let Some(&(px, py)) = path.last() {
+            if px != x && py != y {
+                // diagonal step, jog sideways first so the corridor stays walkable
+                path.push(if randr(0..2) == 0 { (x, py) } else { (px, y) });
+            }
+        }
+        path.push((x, y));
+    }
+    path
+}
+
+fn drunken(from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+    let (mut x, mut y) = from;
+    let mut path = vec![from];
+    // give up wandering eventually, the rest is finished with an L
+    let max_steps = 10 * ((to.0 - x).abs() + (to.1 - y).abs()) + 100;
+    for _ in 0..max_steps {
+        if (x, y) == to {
+            return path;
+        }
+        let (dx, dy) = if randr(0..100) < DRUNKEN_BIAS {
+            let (tx, ty) = ((to.0 - x).signum(), (to.1 - y).signum());
+            // pick an axis that still has distance to cover
+            if ty == 0 || (tx != 0 && randr(0..2) == 0) {
+                (tx, 0)
+            } else {
+                (0, ty)
+            }
+        } else {
+            pick(&[(1, 0), (-1, 0), (0, 1), (0, -1)])
+        };
+        if inside(x + dx, y + dy) {
+            x += dx;
+            y += dy;
+            path.push((x, y));
+        }
+    }
+    path.extend(l_shaped((x, y), to).into_iter().skip(1));
+    path
+}
+
+fn astar(m: &Map, from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+    if !in_bounds(from.0, from.1) || !in_bounds(to.0, to.1) {
+        return l_shaped(from, to);
+    }
+    let idx = |(x, y): (i32, i32)| (y * COLS + x) as usize;
+    let heuristic = |(x, y): (i32, i32)| ((to.0 - x).abs() + (to.1 - y).abs()) * ASTAR_WALL_COST;
+    let cost = |x: i32, y: i32| {
+        if is_floor(m, x, y) {
+            ASTAR_FLOOR_COST
+        } else if [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+            .iter()
+            .any(|&(nx, ny)| is_floor(m, nx, ny))
+        {
+            ASTAR_ROOM_SIDE_COST
+        } else {
+            ASTAR_WALL_COST
+        }
+    };
+
+    let mut dist = vec![i32::MAX; (COLS * ROWS) as usize];
+    let mut came_from: Vec<Option<(i32, i32)>> = vec![None; (COLS * ROWS) as usize];
+    let mut open = BinaryHeap::new();
+    dist[idx(from)] = 0;
+    open.push(Reverse((heuristic(from), from)));
+
+    while let Some(Reverse((_, curr))) = open.pop() {
+        if curr == to {
+            break;
+        }
+        let (cx, cy) = curr;
+        for next in [(cx - 1, cy), (cx + 1, cy), (cx, cy - 1), (cx, cy + 1)] {
+            // the end tiles may lie on the border, everything in between may not
+            if !inside(next.0, next.1) && next != to {
+                continue;
+            }
+            let d = dist[idx(curr)] + cost(next.0, next.1);
+            if d < dist[idx(next)] {
+                dist[idx(next)] = d;
+                came_from[idx(next)] = Some(curr);
+                open.push(Reverse((d + heuristic(next), next)));
+            }
+        }
+    }
+
+    if from != to && came_from[idx(to)].is_none() {
+        return l_shaped(from, to);
+    }
+    let mut path = vec![to];
+    let mut curr = to;
+    while let Some(prev) = came_from[idx(curr)] {
+        path.push(prev);
+        curr = prev;
+    }
+    path.reverse();
+    path
+}
+
+/// corridor_path returns the tiles of a 1 wide corridor from one point to another, both included
+pub fn corridor_path(
+    m: &Map,
+    from: (i32, i32),
+    to: (i32, i32),
+    style: CorridorStyle,
+) -> Vec<(i32, i32)> {
+    match style {
+        CorridorStyle::LShaped => l_shaped(from, to),
+        CorridorStyle::Straight => straight(from, to),
+        CorridorStyle::Drunken => drunken(from, to),
+        CorridorStyle::AStar => astar(m, from, to),
+    }
+}
+
+/// carve_corridor carves a corridor of the given style & width, returns its tiles
+pub fn carve_corridor(
+    m: &mut Map,
+    from: (i32, i32),
+    to: (i32, i32),
+    params: &CorridorParams,
+) -> Vec<(i32, i32)> {
+    let path = corridor_path(m, from, to, params.style);
+    let width = params.width.max(1);
+    let offset = (width - 1) / 2;
+    let mut covered = new_map(TileType::Wall);
+    let mut tiles: Vec<(i32, i32)> = Vec::new();
+    for &(px, py) in &path {
+        for y in py - offset..py - offset + width {
+            for x in px - offset..px - offset + width {
+                // the path itself is carved even on the border, widening stays inside
+                let on_path = (x, y) == (px, py);
+                if !((on_path && in_bounds(x, y)) || inside(x, y)) {
+                    continue;
+                }
+                if covered[y as usize][x as usize] == TileType::Wall as i32 {
+                    covered[y as usize][x as usize] = TileType::Floor as i32;
+                    tiles.push((x, y));
+                }
+                m[y as usize][x as usize] = TileType::Floor as i32;
+            }
+        }
+    }
+    tiles
+}
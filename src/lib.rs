@@ -0,0 +1,11 @@
+pub mod autotile;
+pub mod connections;
+pub mod corridor;
+pub mod fundamentals;
+pub mod mapfile;
+pub mod maptools;
+pub mod maze;
+pub mod metrics;
+pub mod postprocess;
+pub mod procgen;
+pub mod utils;
@@ -1,17 +1,32 @@
-mod fundamentals;
-mod maptools;
-mod procgen;
-mod utils;
-use fundamentals::{COLS, ROWS, TILESIZE, WINH, WINW};
+mod viewer;
+
 use macroquad::prelude::*;
-use maptools::{new_map, randomize_map, Map, TileType};
-use procgen::bsp_tree::BSPTreeGenerator;
-use procgen::cellular_automata::CellularAutomataGenerator;
-use procgen::maze_with_rooms::MazeGenerator;
-use procgen::room_placement::RoomPlacementGenerator;
-use procgen::rwalk::RandomWalkGenerator;
-use procgen::tunneling::TunnelingGenerator;
-use std::collections::HashMap;
+use mapgen::fundamentals::{WINH, WINW};
+use mapgen::mapfile::{load_map, save_map};
+use mapgen::maptools::{new_map, randomize_map, TileType};
+use mapgen::metrics::MapMetrics;
+use mapgen::postprocess::{remove_dead_ends, DeadEndRemoval};
+use mapgen::procgen::{DebugInfo, Generator};
+use viewer::camera::MapCamera;
+#[cfg(not(target_arch = "wasm32"))]
+use viewer::capture::Capture;
+use viewer::compare::{resize_grid, CompareMode, Comparison, DEFAULT_GRID};
+use viewer::editor::Editor;
+use viewer::history::History;
+use viewer::overlay::Overlays;
+use viewer::panel::{draw_params_panel, mouse_over_panel};
+use viewer::theme::{load_themes, render_map};
+
+const MAP_FILE: &str = "mapgen_map.txt";
+
+const GENERATOR_KEYS: [KeyCode; 6] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+];
 
 fn window_conf() -> Conf {
     Conf {
@@ -24,32 +39,20 @@ fn window_conf() -> Conf {
     }
 }
 
-fn render_map(tiles: &HashMap<TileType, DrawTextureParams>, texture: Texture2D, m: &Map) {
-    for row in 0..ROWS {
-        for col in 0..COLS {
-            let curr_tile = m[row as usize][col as usize];
-            let curr_type = match curr_tile {
-                0 => TileType::Wall,
-                1 => TileType::Floor,
-                _ => TileType::Hero,
-            };
-            draw_texture_ex(
-                texture,
-                col as f32 * TILESIZE as f32,
-                row as f32 * TILESIZE as f32,
-                WHITE,
-                tiles.get(&curr_type).unwrap().clone(),
-            );
-        }
-    }
+fn new_seed() -> u64 {
+    (macroquad::miniquad::date::now() * 1_000_000.0) as u64
 }
 
 fn render_help_full(params: TextParams) {
-    const HELP_TEXT: [&str; 4] = [
-        "[r] - randomize map  [1] - tunneling       [5] - room placement",
-        "[c] - clear map      [2] - BSP             [6] - maze with rooms",
-        "[f] - toggle FPS     [3] - random walk     [LShift+num] - frenzy",
-        "[h] - toggle help    [4] - cell. automata  [ESC] - quit",
+    const HELP_TEXT: [&str; 8] = [
+        "[r] - randomize map   [1] - tunneling        [LShift+num] - frenzy     [z] - fit to window  [[/]] - brush size",
+        "[c] - clear map       [2] - BSP              [p] - params panel        [drag] - pan         [ctrl+z/y] - undo/redo",
+        "[f] - toggle FPS      [3] - random walk      [d] - remove dead ends    [wheel] - zoom       [ctrl+s/o] - save/load",
+        "[h] - toggle help     [4] - cell. automata   [l] - dead ends to loops  [e] - toggle editor  [v] - compare mode",
+        "[m] - toggle metrics  [5] - room placement   [a] - autotiling          [tab] - next tool    [-/=] - grid columns",
+        "[s] - screenshot      [6] - maze with rooms  [t] - next theme          [q] - next tile      [n] - new seeds      [LShift+-/=] - grid rows",
+        "[x] - record GIF      [ESC] - quit           [left/right] - history    [k] - pin entry      [ctrl+e] - export entry",
+        "[F1-F5] - overlays: BSP partitions, rooms, connection points, regions, distances from cursor",
     ];
     for (idx, row) in HELP_TEXT.iter().enumerate() {
         draw_text_ex(
@@ -61,50 +64,40 @@ fn render_help_full(params: TextParams) {
     }
 }
 
+fn render_metrics(metrics: &MapMetrics, params: TextParams) {
+    let rows = [
+        format!("floor ratio:    {:.2}", metrics.floor_ratio),
+        format!("regions:        {}", metrics.regions),
+        format!("rooms:          {}", metrics.rooms),
+        format!("corridor len.:  {}", metrics.corridor_length),
+        format!("dead ends:      {}", metrics.dead_ends),
+        format!("loops:          {}", metrics.loops),
+        format!("avg. distance:  {:.1}", metrics.avg_walk_distance),
+        format!("max. distance:  {}", metrics.max_walk_distance),
+        format!("chokepoints:    {}", metrics.chokepoints),
+    ];
+    let x = WINW as f32 - 210.0;
+    draw_rectangle(
+        x - 10.0,
+        10.0,
+        210.0,
+        rows.len() as f32 * 19.0 + 12.0,
+        Color::from_rgba(40, 40, 40, 200),
+    );
+    for (idx, row) in rows.iter().enumerate() {
+        draw_text_ex(row, x, 30.0 + idx as f32 * 19.0, params);
+    }
+}
+
 #[macroquad::main(window_conf)]
 async fn main() {
     // seed PRNG
     rand::srand(macroquad::miniquad::date::now() as _);
 
-    // load texture tile sheet
-    let texture: Texture2D = load_texture("assets/tilesheet_colored.png").await.unwrap();
-    texture.set_filter(FilterMode::Nearest);
-
-    // setup DrawTextureParams for individual tile types
-    let tile_wall: DrawTextureParams = DrawTextureParams {
-        source: Some(Rect::new(
-            0.0 * TILESIZE as f32,
-            0.0,
-            TILESIZE as f32,
-            TILESIZE as f32,
-        )),
-        ..Default::default()
-    };
-    let tile_floor: DrawTextureParams = DrawTextureParams {
-        source: Some(Rect::new(
-            1.0 * TILESIZE as f32,
-            0.0,
-            TILESIZE as f32,
-            TILESIZE as f32,
-        )),
-        ..Default::default()
-    };
-    let tile_hero: DrawTextureParams = DrawTextureParams {
-        source: Some(Rect::new(
-            2.0 * TILESIZE as f32,
-            0.0,
-            TILESIZE as f32,
-            TILESIZE as f32,
-        )),
-        ..Default::default()
-    };
-
-    // store each tiles' DrawTextureParams with their respective TileType as key
-    let tiles: HashMap<TileType, DrawTextureParams> = HashMap::from([
-        (TileType::Wall, tile_wall),
-        (TileType::Floor, tile_floor),
-        (TileType::Hero, tile_hero),
-    ]);
+    // load tileset themes
+    let themes = load_themes("assets/themes.ron").await;
+    let mut theme_idx = 0;
+    let mut theme_switched_at = f64::MIN;
 
     // load font
     let font: Font = load_ttf_font("assets/Hack-Regular.ttf").await.unwrap();
@@ -122,18 +115,99 @@ async fn main() {
 
     let mut show_help = true;
     let mut show_fps = true;
+    let mut show_metrics = false;
+    let mut autotiling = true;
+
+    // metrics are only recomputed when the map changes
+    let mut metrics = MapMetrics::from_map(&map);
+    let mut measured_map = map;
+
+    let mut view = MapCamera::new();
+
+    // every generator keeps its own parameters, edited in the params panel
+    let mut generators = Generator::all();
+    let mut active: Option<usize> = None;
+    let mut seed: u64 = 0;
+    let mut show_panel = false;
+
+    let mut editor = Editor::new();
+    let mut editing = false;
+    // message shown for a few seconds, along with the time it was set
+    let mut message: Option<(String, f64)> = None;
+
+    let mut comparison: Option<Comparison> = None;
+    let mut grid = DEFAULT_GRID;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut capture = Capture::new();
+
+    // every map that replaces the current one is kept, to step back & forth through
+    let mut history = History::new();
+
+    // what the generator built the current map from, shown by the overlays
+    let mut debug = DebugInfo::default();
+    let mut overlays = Overlays::new();
 
     // main loop
     loop {
         let bg = Color::from_rgba(40, 40, 40, 255);
         clear_background(bg);
 
-        render_map(&tiles, texture, &map);
+        if mouse_over_panel() {
+            view.update(&[]);
+        } else if let Some(c) = comparison.as_mut() {
+            if is_mouse_button_pressed(MouseButton::Left) {
+                if let Some(idx) = c.pane_at(Vec2::from(mouse_position())) {
+                    c.selected = idx;
+                }
+            }
+            view.update(&[MouseButton::Left, MouseButton::Middle]);
+        } else if editing {
+            // left mouse button is taken by the editor tools
+            view.update(&[MouseButton::Middle, MouseButton::Right]);
+            editor.update(&mut map, &view);
+        } else {
+            view.update(&[MouseButton::Left, MouseButton::Middle]);
+        }
+        if let Some(c) = &comparison {
+            c.draw(&themes[theme_idx], autotiling, &view, font_params);
+        } else {
+            set_camera(&view.camera());
+            render_map(&themes[theme_idx], &map, autotiling, view.visible_tiles());
+            overlays.draw(&map, &debug, &view);
+            if editing {
+                editor.draw_preview(&view);
+            }
+            set_default_camera();
+        }
 
         if show_fps {
             draw_text_ex(&format!("FPS: {}", current_fps), 10.0, 26.0, font_params);
         }
 
+        // briefly show the name of the theme after switching
+        if get_time() - theme_switched_at < 2.0 {
+            let name = format!("theme: {}", themes[theme_idx].name);
+            draw_text_ex(&name, 10.0, 45.0, font_params);
+        }
+
+        if editing && comparison.is_none() {
+            draw_text_ex(&editor.status(), 10.0, 64.0, font_params);
+        }
+        if let Some((text, since)) = &message {
+            if get_time() - since < 3.0 {
+                draw_text_ex(text, 10.0, 83.0, font_params);
+            }
+        }
+
+        if show_metrics && comparison.is_none() {
+            if measured_map != map {
+                metrics = MapMetrics::from_map(&map);
+                measured_map = map;
+            }
+            render_metrics(&metrics, font_params);
+        }
+
         if show_help {
             render_help_full(font_params);
         } else {
@@ -142,61 +216,228 @@ async fn main() {
             draw_text_ex("[h]", 10.0, WINH as f32 - 16.0, gray_text);
         }
 
+        // capture before drawing the recording indicator, so it doesn't end up in the images
+        let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if is_key_pressed(KeyCode::S) && !ctrl {
+                message = Some((format!("saved {}", capture.screenshot()), get_time()));
+            }
+            if let Some(text) = capture.update() {
+                message = Some((text, get_time()));
+            }
+            capture.draw_indicator(font_params);
+        }
+        #[cfg(target_arch = "wasm32")]
+        if is_key_pressed(KeyCode::S) && !ctrl {
+            message = Some((
+                "screenshots are not supported on web".to_string(),
+                get_time(),
+            ));
+        }
+
         if is_key_pressed(KeyCode::H) {
             show_help = !show_help;
         }
         if is_key_pressed(KeyCode::F) {
             show_fps = !show_fps;
         }
+        if is_key_pressed(KeyCode::M) {
+            show_metrics = !show_metrics;
+        }
+        if is_key_pressed(KeyCode::A) {
+            autotiling = !autotiling;
+        }
+        if is_key_pressed(KeyCode::P) {
+            show_panel = !show_panel;
+        }
+        if is_key_pressed(KeyCode::Z) && !ctrl {
+            view.fit();
+        }
+        if is_key_pressed(KeyCode::E) && !ctrl {
+            editing = !editing;
+        }
+        if editing && comparison.is_none() {
+            editor.handle_keys();
+            if ctrl && is_key_pressed(KeyCode::Z) {
+                editor.undo(&mut map);
+            }
+            if ctrl && is_key_pressed(KeyCode::Y) {
+                editor.redo(&mut map);
+            }
+        }
+        if ctrl && is_key_pressed(KeyCode::S) {
+            let text = match save_map(MAP_FILE, &map) {
+                Ok(_) => format!("saved {}", MAP_FILE),
+                Err(e) => format!("saving {} failed: {}", MAP_FILE, e),
+            };
+            message = Some((text, get_time()));
+        }
+        if ctrl && is_key_pressed(KeyCode::O) {
+            let text = match load_map(MAP_FILE) {
+                Ok(loaded) => {
+                    history.sync(&map);
+                    editor.snapshot(&map);
+                    map = loaded;
+                    active = None;
+                    debug = DebugInfo::default();
+                    history.push(None, 0, map);
+                    format!("loaded {}", MAP_FILE)
+                }
+                Err(e) => format!("loading {} failed: {}", MAP_FILE, e),
+            };
+            message = Some((text, get_time()));
+        }
+        if is_key_pressed(KeyCode::V) {
+            let mode = match comparison.as_ref().map(|c| c.mode) {
+                None => Some(CompareMode::Generators),
+                Some(CompareMode::Generators) => Some(CompareMode::Seeds),
+                Some(CompareMode::Seeds) => Some(CompareMode::Params),
+                Some(CompareMode::Params) => None,
+            };
+            comparison = mode.map(|mode| {
+                Comparison::new(mode, grid, &generators, active.unwrap_or(0), new_seed())
+            });
+            if let Some(mode) = mode {
+                message = Some((format!("compare {}", mode.name()), get_time()));
+            }
+        }
+        if let Some(mode) = comparison.as_ref().map(|c| c.mode) {
+            // -/= remove or add a pane column, with LShift a row
+            let step = match (
+                is_key_pressed(KeyCode::Minus),
+                is_key_pressed(KeyCode::Equal),
+            ) {
+                (true, false) => -1,
+                (false, true) => 1,
+                _ => 0,
+            };
+            let resized = match is_key_down(KeyCode::LeftShift) {
+                true => resize_grid(grid, 0, step),
+                false => resize_grid(grid, step, 0),
+            };
+            if resized != grid || is_key_pressed(KeyCode::N) {
+                grid = resized;
+                let c = Comparison::new(mode, grid, &generators, active.unwrap_or(0), new_seed());
+                comparison = Some(c);
+            }
+        }
+        if let Some(text) = overlays.handle_keys() {
+            message = Some((text, get_time()));
+        }
+        if is_key_pressed(KeyCode::X) {
+            #[cfg(not(target_arch = "wasm32"))]
+            let text = capture.toggle_recording();
+            #[cfg(target_arch = "wasm32")]
+            let text = "recording is not supported on web".to_string();
+            message = Some((text, get_time()));
+        }
+        if is_key_pressed(KeyCode::T) {
+            theme_idx = (theme_idx + 1) % themes.len();
+            theme_switched_at = get_time();
+        }
         if is_key_pressed(KeyCode::C) {
+            history.sync(&map);
+            editor.snapshot(&map);
+            active = None;
             map = new_map(TileType::Floor);
+            debug = DebugInfo::default();
+            history.push(None, 0, map);
         }
         if is_key_pressed(KeyCode::R) {
+            history.sync(&map);
+            editor.snapshot(&map);
+            active = None;
             map = randomize_map();
+            debug = DebugInfo::default();
+            history.push(None, 0, map);
         }
-        if is_key_pressed(KeyCode::Key1) {
-            map = TunnelingGenerator::generate_map(6, 16, 30);
-        }
-        if is_key_pressed(KeyCode::Key2) {
-            map = BSPTreeGenerator::generate_map();
+        if is_key_pressed(KeyCode::Left) || is_key_pressed(KeyCode::Right) {
+            let entry = match is_key_pressed(KeyCode::Left) {
+                true => history.back(&map),
+                false => history.forward(&map),
+            };
+            if let Some(entry) = entry {
+                map = entry.map;
+                editor.clear();
+                seed = entry.seed;
+                // regenerating with the entry's seed brings back its debug info
+                debug = match entry.generator {
+                    Some(g) => g.generate_seeded_debug(seed).1,
+                    None => DebugInfo::default(),
+                };
+                // the params panel continues from the entry's parameters
+                active = entry.generator.map(|g| {
+                    generators[g.index()] = g;
+                    g.index()
+                });
+                message = Some((history.status(), get_time()));
+            }
         }
-        if is_key_pressed(KeyCode::Key3) {
-            map = RandomWalkGenerator::generate_map();
+        if is_key_pressed(KeyCode::K) && history.toggle_pin(&map).is_some() {
+            message = Some((history.status(), get_time()));
         }
-        if is_key_pressed(KeyCode::Key4) {
-            map = CellularAutomataGenerator::generate_map();
+        if ctrl && is_key_pressed(KeyCode::E) {
+            let text = match history.export(&map) {
+                Ok(path) => format!("exported {}", path),
+                Err(e) => format!("exporting failed: {}", e),
+            };
+            message = Some((text, get_time()));
         }
-        if is_key_pressed(KeyCode::Key5) {
-            map = RoomPlacementGenerator::generate_map();
+        if is_key_pressed(KeyCode::D) {
+            editor.snapshot(&map);
+            remove_dead_ends(&mut map, DeadEndRemoval::Full);
         }
-        if is_key_pressed(KeyCode::Key6) {
-            map = MazeGenerator::generate_map();
+        if is_key_pressed(KeyCode::L) {
+            editor.snapshot(&map);
+            remove_dead_ends(&mut map, DeadEndRemoval::Loops { max_distance: 6 });
         }
         if is_key_pressed(KeyCode::Escape) {
             break;
         }
 
-        if is_key_down(KeyCode::LeftShift) {
-            if is_key_down(KeyCode::R) {
-                map = randomize_map();
-            }
-            if is_key_down(KeyCode::Key1) {
-                map = TunnelingGenerator::generate_map(6, 16, 30);
-            }
-            if is_key_down(KeyCode::Key2) {
-                map = BSPTreeGenerator::generate_map();
-            }
-            if is_key_down(KeyCode::Key3) {
-                map = RandomWalkGenerator::generate_map();
-            }
-            if is_key_down(KeyCode::Key4) {
-                map = CellularAutomataGenerator::generate_map();
-            }
-            if is_key_down(KeyCode::Key5) {
-                map = RoomPlacementGenerator::generate_map();
+        if is_key_down(KeyCode::LeftShift) && is_key_down(KeyCode::R) {
+            history.sync(&map);
+            editor.snapshot(&map);
+            active = None;
+            map = randomize_map();
+            debug = DebugInfo::default();
+            history.push(None, 0, map);
+        }
+
+        // number keys generate a new map, holding LShift regenerates every frame (frenzy)
+        for (idx, key) in GENERATOR_KEYS.iter().enumerate() {
+            if is_key_pressed(*key) || (is_key_down(KeyCode::LeftShift) && is_key_down(*key)) {
+                active = Some(idx);
+                seed = new_seed();
+                history.sync(&map);
+                editor.snapshot(&map);
+                (map, debug) = generators[idx].generate_seeded_debug(seed);
+                history.push(Some(generators[idx]), seed, map);
+
+                // panes comparing one generator switch to the new one
+                if let Some(mode) = comparison.as_ref().map(|c| c.mode) {
+                    if mode != CompareMode::Generators {
+                        comparison = Some(Comparison::new(mode, grid, &generators, idx, seed));
+                    }
+                }
             }
-            if is_key_down(KeyCode::Key6) {
-                map = MazeGenerator::generate_map();
+        }
+
+        if show_panel {
+            if let Some(c) = comparison.as_mut() {
+                // the selected pane's parameters are edited independently
+                let pane = &mut c.panes[c.selected];
+                if draw_params_panel(&mut pane.generator, pane.seed) {
+                    pane.regenerate();
+                }
+            } else if let Some(idx) = active {
+                // regenerate with the same seed, so only the parameter change shows
+                if draw_params_panel(&mut generators[idx], seed) {
+                    (map, debug) = generators[idx].generate_seeded_debug(seed);
+                    editor.clear();
+                    history.replace_current(Some(generators[idx]), seed, map);
+                }
             }
         }
 
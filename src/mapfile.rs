@@ -0,0 +1,88 @@
+use crate::{fundamentals::*, maptools::*};
+use std::fmt;
+use std::fs;
+
+/// Maps are stored as plain text, one line per row, one character per tile
+pub const WALL_CHAR: char = '#';
+pub const FLOOR_CHAR: char = '.';
+pub const HERO_CHAR: char = '@';
+pub const DOOR_CHAR: char = '+';
+
+#[derive(Debug)]
+pub enum MapFileError {
+    Io(std::io::Error),
+    WrongSize { rows: usize, cols: usize },
+    UnknownTile(char),
+}
+
+impl fmt::Display for MapFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapFileError::Io(e) => write!(f, "{}", e),
+            MapFileError::WrongSize { rows, cols } => write!(
+                f,
+                "map is {}x{} tiles, expected {}x{}",
+                cols, rows, COLS, ROWS
+            ),
+            MapFileError::UnknownTile(c) => write!(f, "unknown tile '{}'", c),
+        }
+    }
+}
+
+impl std::error::Error for MapFileError {}
+
+impl From<std::io::Error> for MapFileError {
+    fn from(e: std::io::Error) -> Self {
+        MapFileError::Io(e)
+    }
+}
+
+pub fn map_to_string(m: &Map) -> String {
+    let mut s = String::with_capacity(((COLS + 1) * ROWS) as usize);
+    for row in m.iter() {
+        for &tile in row.iter() {
+            s.push(match tile {
+                0 => WALL_CHAR,
+                1 => FLOOR_CHAR,
+                3 => DOOR_CHAR,
+                _ => HERO_CHAR,
+            });
+        }
+        s.push('\n');
+    }
+    s
+}
+
+pub fn map_from_str(s: &str) -> Result<Map, MapFileError> {
+    let lines: Vec<&str> = s.lines().filter(|l| !l.is_empty()).collect();
+    let cols = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    if lines.len() != ROWS as usize || lines.iter().any(|l| l.chars().count() != COLS as usize) {
+        return Err(MapFileError::WrongSize {
+            rows: lines.len(),
+            cols,
+        });
+    }
+
+    let mut map = new_map(TileType::Wall);
+    for (y, line) in lines.iter().enumerate() {
+        for (x, c) in line.chars().enumerate() {
+            map[y][x] = match c {
+                WALL_CHAR => TileType::Wall as i32,
+                FLOOR_CHAR => TileType::Floor as i32,
+                HERO_CHAR => TileType::Hero as i32,
+                DOOR_CHAR => TileType::Door as i32,
+                _ => return Err(MapFileError::UnknownTile(c)),
+            };
+        }
+    }
+    Ok(map)
+}
+
+pub fn save_map(path: &str, m: &Map) -> Result<(), MapFileError> {
+    fs::write(path, map_to_string(m))?;
+    Ok(())
+}
+
+pub fn load_map(path: &str) -> Result<Map, MapFileError> {
+    map_from_str(&fs::read_to_string(path)?)
+}
@@ -1,11 +1,14 @@
 use crate::fundamentals::*;
 use crate::utils::*;
+use serde::Deserialize;
+use std::collections::VecDeque;
 
-#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Deserialize)]
 pub enum TileType {
     Wall,
     Floor,
     Hero,
+    Door,
 }
 
 pub type Map = [[i32; COLS as usize]; ROWS as usize];
@@ -24,7 +27,7 @@ pub fn randomize_map() -> Map {
     map
 }
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Rect {
     pub x: i32,
     pub y: i32,
@@ -56,6 +59,15 @@ impl Room {
         (cx, cy)
     }
 
+    pub fn rect(&self) -> Rect {
+        Rect {
+            x: self.x1,
+            y: self.y1,
+            w: self.x2 - self.x1,
+            h: self.y2 - self.y1,
+        }
+    }
+
     pub fn overlaps(&self, other: Room) -> bool {
         self.x1 <= other.x2 && self.x2 >= other.x1 && self.y1 <= other.y2 && self.y2 >= other.y1
     }
@@ -84,3 +96,83 @@ pub fn carve_vert_tunnel(m: &mut Map, y1: i32, y2: i32, x: i32) {
         m[y as usize][x as usize] = TileType::Floor as i32;
     }
 }
+
+/// is_floor returns true if (x, y) is in bounds and holds a floor tile,
+/// doors count as floor since they can be walked through
+pub fn is_floor(m: &Map, x: i32, y: i32) -> bool {
+    in_bounds(x, y)
+        && (m[y as usize][x as usize] == TileType::Floor as i32
+            || m[y as usize][x as usize] == TileType::Door as i32)
+}
+
+/// label_regions flood fills every orthogonally connected floor area,
+/// returns a map of region labels (0 for walls, 1..=n for regions) and the number of regions
+pub fn label_regions(m: &Map) -> (Map, i32) {
+    let mut labels = new_map(TileType::Wall);
+    let mut num_regions = 0;
+    let mut stack: Vec<(i32, i32)> = Vec::new();
+
+    for y in 0..ROWS {
+        for x in 0..COLS {
+            if !is_floor(m, x, y) || labels[y as usize][x as usize] != 0 {
+                continue;
+            }
+            num_regions += 1;
+            labels[y as usize][x as usize] = num_regions;
+            stack.push((x, y));
+            while let Some((cx, cy)) = stack.pop() {
+                for (nx, ny) in [(cx - 1, cy), (cx + 1, cy), (cx, cy - 1), (cx, cy + 1)] {
+                    if is_floor(m, nx, ny) && labels[ny as usize][nx as usize] == 0 {
+                        labels[ny as usize][nx as usize] = num_regions;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+        }
+    }
+
+    (labels, num_regions)
+}
+
+/// distance_map runs a BFS over the floor tiles starting at (x, y),
+/// returns the walking distance to every tile, -1 for walls & unreachable tiles
+pub fn distance_map(m: &Map, x: i32, y: i32) -> Map {
+    let mut dist = [[-1; COLS as usize]; ROWS as usize];
+    if !is_floor(m, x, y) {
+        return dist;
+    }
+    let mut queue: VecDeque<(i32, i32)> = VecDeque::new();
+    dist[y as usize][x as usize] = 0;
+    queue.push_back((x, y));
+    while let Some((cx, cy)) = queue.pop_front() {
+        let d = dist[cy as usize][cx as usize];
+        for (nx, ny) in [(cx - 1, cy), (cx + 1, cy), (cx, cy - 1), (cx, cy + 1)] {
+            if is_floor(m, nx, ny) && dist[ny as usize][nx as usize] == -1 {
+                dist[ny as usize][nx as usize] = d + 1;
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+    dist
+}
+
+/// flood_fill replaces the orthogonally connected area of same tiles around (x, y) with the given tile
+pub fn flood_fill(m: &mut Map, x: i32, y: i32, fill_with: TileType) {
+    if !in_bounds(x, y) {
+        return;
+    }
+    let target = m[y as usize][x as usize];
+    if target == fill_with as i32 {
+        return;
+    }
+    let mut stack: Vec<(i32, i32)> = vec![(x, y)];
+    m[y as usize][x as usize] = fill_with as i32;
+    while let Some((cx, cy)) = stack.pop() {
+        for (nx, ny) in [(cx - 1, cy), (cx + 1, cy), (cx, cy - 1), (cx, cy + 1)] {
+            if in_bounds(nx, ny) && m[ny as usize][nx as usize] == target {
+                m[ny as usize][nx as usize] = fill_with as i32;
+                stack.push((nx, ny));
+            }
+        }
+    }
+}
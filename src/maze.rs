@@ -0,0 +1,577 @@
+use crate::{fundamentals::*, maptools::*, utils::*};
+use serde::Serialize;
+
+/// chance (0-100) for the mixed growing tree to continue from the newest cell
+const MIXED_NEWEST_CHANCE: i32 = 75;
+/// chance (0-100) for Eller's algorithm to join neighbor cells in a row, or to extend a set down
+const ELLER_JOIN_CHANCE: i32 = 50;
+
+/// size of the maze grid with 1 tile wide corridors, wider corridors make for fewer cells
+const GRID_W: i32 = (COLS - 1) / 2;
+const GRID_H: i32 = (ROWS - 1) / 2;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum CellSelection {
+    /// the newest cell, same as the backtracker
+    Newest,
+    /// a random cell, same as Prim's
+    Random,
+    /// mostly the newest, sometimes a random cell
+    Mixed,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum MazeAlgorithm {
+    /// randomized depth-first search, long winding corridors
+    Backtracker,
+    Prim,
+    Kruskal,
+    Eller,
+    GrowingTree(CellSelection),
+    Wilson,
+    BinaryTree,
+    HuntAndKill,
+}
+
+/// carve_maze fills the walls of the map with maze passages, keeping away from existing floor.
+/// Maps without floor become a pure maze. Passages are corridor_width tiles wide.
+/// The backtracker grows tile by tile, unless the maze is aligned to the grid or has wider
+/// passages, then it runs on the cell grid like the other algorithms.
+pub fn carve_maze(m: &mut Map, algorithm: MazeAlgorithm, corridor_width: i32, aligned: bool) {
+    let corridor_width = corridor_width.max(1);
+    if algorithm == MazeAlgorithm::Backtracker && !aligned && corridor_width == 1 {
+        backtrack(m);
+        return;
+    }
+    let mut grid = Grid::new(m, corridor_width);
+    match algorithm {
+        // the growing tree continuing from the newest cell is a backtracker
+        MazeAlgorithm::Backtracker => growing_tree(&mut grid, m, CellSelection::Newest),
+        MazeAlgorithm::Prim => prim(&mut grid, m),
+        MazeAlgorithm::Kruskal => {}
+        MazeAlgorithm::Eller => eller(&mut grid, m),
+        MazeAlgorithm::GrowingTree(selection) => growing_tree(&mut grid, m, selection),
+        MazeAlgorithm::Wilson => wilson(&mut grid, m),
+        MazeAlgorithm::BinaryTree => binary_tree(&grid, m),
+        MazeAlgorithm::HuntAndKill => hunt_and_kill(&mut grid, m),
+    }
+    // join whatever the algorithm left apart, for Kruskal's this is all of it
+    join_forest(&grid, m);
+}
+
+/// Grid is the maze cell grid over the map, a cell can be carved if it has no floor around it.
+/// Cells are width x width tiles, the cell at (cx, cy) starts at tile (1 + cx * (width + 1), ..)
+/// so with 1 tile wide corridors every cell is on odd coordinates.
+struct Grid {
+    open: Vec<bool>,
+    visited: Vec<bool>,
+    width: i32,
+    cols: i32,
+    rows: i32,
+}
+
+impl Grid {
+    fn new(m: &Map, width: i32) -> Self {
+        let mut grid = Self {
+            open: vec![false; (GRID_W * GRID_H) as usize],
+            visited: vec![false; (GRID_W * GRID_H) as usize],
+            width,
+            cols: (COLS - 1) / (width + 1),
+            rows: (ROWS - 1) / (width + 1),
+        };
+        for cy in 0..grid.rows {
+            for cx in 0..grid.cols {
+                let (x, y) = grid.tile((cx, cy));
+                grid.open[idx((cx, cy))] = (-1..width + 1).all(|j| {
+                    (-1..width + 1).all(|i| in_bounds(x + i, y + j) && !is_floor(m, x + i, y + j))
+                });
+            }
+        }
+        // a cell without open neighbors would be an unreachable speck of floor
+        for c in grid.cells() {
+            if grid.neighbors(c).is_empty() {
+                grid.open[idx(c)] = false;
+            }
+        }
+        grid
+    }
+
+    fn is_open(&self, c: (i32, i32)) -> bool {
+        (0..self.cols).contains(&c.0) && (0..self.rows).contains(&c.1) && self.open[idx(c)]
+    }
+
+    fn neighbors(&self, c: (i32, i32)) -> Vec<(i32, i32)> {
+        [
+            (c.0, c.1 - 1),
+            (c.0 + 1, c.1),
+            (c.0, c.1 + 1),
+            (c.0 - 1, c.1),
+        ]
+        .into_iter()
+        .filter(|&n| self.is_open(n))
+        .collect()
+    }
+
+    fn cells(&self) -> Vec<(i32, i32)> {
+        (0..self.rows)
+            .flat_map(|cy| (0..self.cols).map(move |cx| (cx, cy)))
+            .filter(|&c| self.is_open(c))
+            .collect()
+    }
+
+    /// tile returns the top left tile of a cell
+    fn tile(&self, c: (i32, i32)) -> (i32, i32) {
+        (1 + c.0 * (self.width + 1), 1 + c.1 * (self.width + 1))
+    }
+
+    /// fill carves the tiles of a cell
+    fn fill(&self, m: &mut Map, c: (i32, i32)) {
+        self.carve(m, c, c);
+    }
+
+    /// visit marks a cell as part of the maze & carves it
+    fn visit(&mut self, m: &mut Map, c: (i32, i32)) {
+        self.visited[idx(c)] = true;
+        self.fill(m, c);
+    }
+
+    /// carve opens both cells & the wall between them
+    fn carve(&self, m: &mut Map, a: (i32, i32), b: (i32, i32)) {
+        let (ax, ay) = self.tile(a);
+        let (bx, by) = self.tile(b);
+        for y in ay.min(by)..ay.max(by) + self.width {
+            for x in ax.min(bx)..ax.max(bx) + self.width {
+                m[y as usize][x as usize] = TileType::Floor as i32;
+            }
+        }
+    }
+
+    fn is_carved(&self, m: &Map, a: (i32, i32), b: (i32, i32)) -> bool {
+        let (ax, ay) = self.tile(a);
+        let (bx, by) = self.tile(b);
+        if ay == by {
+            is_floor(m, ax.min(bx) + self.width, ay)
+        } else {
+            is_floor(m, ax, ay.min(by) + self.width)
+        }
+    }
+}
+
+fn idx(c: (i32, i32)) -> usize {
+    (c.1 * GRID_W + c.0) as usize
+}
+
+/// Sets is a disjoint set forest over the grid cells
+struct Sets {
+    parent: Vec<usize>,
+}
+
+impl Sets {
+    fn new() -> Self {
+        Self {
+            parent: (0..(GRID_W * GRID_H) as usize).collect(),
+        }
+    }
+
+    fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        // path compression
+        let mut curr = i;
+        while self.parent[curr] != root {
+            let next = self.parent[curr];
+            self.parent[curr] = root;
+            curr = next;
+        }
+        root
+    }
+
+    /// union joins the sets of both cells, returns false if they already were one
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let (ra, rb) = (self.find(a), self.find(b));
+        self.parent[ra] = rb;
+        ra != rb
+    }
+}
+
+/// join_forest carves random walls between cells that aren't connected yet (Kruskal's),
+/// until every connected area of open cells is a single tree
+fn join_forest(grid: &Grid, m: &mut Map) {
+    let mut sets = Sets::new();
+    let mut walls: Vec<((i32, i32), (i32, i32))> = Vec::new();
+    for c in grid.cells() {
+        for n in [(c.0 + 1, c.1), (c.0, c.1 + 1)] {
+            if !grid.is_open(n) {
+                continue;
+            }
+            if grid.is_carved(m, c, n) {
+                sets.union(idx(c), idx(n));
+            } else {
+                walls.push((c, n));
+            }
+        }
+    }
+    shuffle(&mut walls);
+    for (a, b) in walls {
+        if sets.union(idx(a), idx(b)) {
+            grid.carve(m, a, b);
+        }
+    }
+}
+
+fn prim(grid: &mut Grid, m: &mut Map) {
+    for start in grid.cells() {
+        if grid.visited[idx(start)] {
+            continue;
+        }
+        grid.visit(m, start);
+        let mut frontier: Vec<((i32, i32), (i32, i32))> = grid
+            .neighbors(start)
+            .into_iter()
+            .map(|n| (start, n))
+            .collect();
+        while !frontier.is_empty() {
+            let i = pick_index(frontier.len());
+            let (from, to) = frontier.swap_remove(i);
+            if grid.visited[idx(to)] {
+                continue;
+            }
+            grid.carve(m, from, to);
+            grid.visit(m, to);
+            frontier.extend(grid.neighbors(to).into_iter().map(|n| (to, n)));
+        }
+    }
+}
+
+fn growing_tree(grid: &mut Grid, m: &mut Map, selection: CellSelection) {
+    for start in grid.cells() {
+        if grid.visited[idx(start)] {
+            continue;
+        }
+        grid.visit(m, start);
+        let mut active = vec![start];
+        while !active.is_empty() {
+            let newest = match selection {
+                CellSelection::Newest => true,
+                CellSelection::Random => false,
+                CellSelection::Mixed => randr(0..100) < MIXED_NEWEST_CHANCE,
+            };
+            let i = if newest {
+                active.len() - 1
+            } else {
+                pick_index(active.len())
+            };
+            let c = active[i];
+            let unvisited: Vec<(i32, i32)> = grid
+                .neighbors(c)
+                .into_iter()
+                .filter(|&n| !grid.visited[idx(n)])
+                .collect();
+            if unvisited.is_empty() {
+                active.remove(i);
+                continue;
+            }
+            let next = pick(&unvisited);
+            grid.carve(m, c, next);
+            grid.visit(m, next);
+            active.push(next);
+        }
+    }
+}
+
+/// eller carves row by row, joining neighbors in a row at random & extending every set
+/// into the next row at least once where it can
+fn eller(grid: &mut Grid, m: &mut Map) {
+    let mut sets = Sets::new();
+    for cy in 0..grid.rows {
+        let last_row = cy == grid.rows - 1;
+        for cx in 0..grid.cols {
+            let c = (cx, cy);
+            if grid.is_open(c) {
+                grid.visit(m, c);
+            }
+            let next = (cx + 1, cy);
+            if grid.is_open(c)
+                && grid.is_open(next)
+                && sets.find(idx(c)) != sets.find(idx(next))
+                && (last_row || randr(0..100) < ELLER_JOIN_CHANCE)
+            {
+                sets.union(idx(c), idx(next));
+                grid.carve(m, c, next);
+            }
+        }
+        if last_row {
+            break;
+        }
+
+        // cells that can extend down, grouped by their set
+        let mut down: Vec<(usize, Vec<(i32, i32)>)> = Vec::new();
+        for cx in 0..grid.cols {
+            let c = (cx, cy);
+            if !grid.is_open(c) || !grid.is_open((cx, cy + 1)) {
+                continue;
+            }
+            let set = sets.find(idx(c));
+            match down.iter_mut().find(|(s, _)| *s == set) {
+                Some((_, cells)) => cells.push(c),
+                None => down.push((set, vec![c])),
+            }
+        }
+        for (_, cells) in down {
+            let first = pick(&cells);
+            for c in cells {
+                if c == first || randr(0..100) < ELLER_JOIN_CHANCE {
+                    let below = (c.0, c.1 + 1);
+                    sets.union(idx(c), idx(below));
+                    grid.carve(m, c, below);
+                }
+            }
+        }
+    }
+}
+
+/// wilson adds loop-erased random walks to the maze until every cell is part of it,
+/// giving an unbiased maze
+fn wilson(grid: &mut Grid, m: &mut Map) {
+    let mut cells = grid.cells();
+    // the walks can't leave their area of open cells, so each area needs a cell to walk to
+    let mut seeded = vec![false; grid.open.len()];
+    for &c in &cells {
+        if seeded[idx(c)] {
+            continue;
+        }
+        grid.visit(m, c);
+        let mut stack = vec![c];
+        seeded[idx(c)] = true;
+        while let Some(curr) = stack.pop() {
+            for n in grid.neighbors(curr) {
+                if !seeded[idx(n)] {
+                    seeded[idx(n)] = true;
+                    stack.push(n);
+                }
+            }
+        }
+    }
+
+    shuffle(&mut cells);
+    // the cell each walk went on to from a cell, later steps overwrite earlier ones
+    let mut walk_next: Vec<Option<(i32, i32)>> = vec![None; grid.open.len()];
+    for start in cells {
+        let mut curr = start;
+        while !grid.visited[idx(curr)] {
+            let next = pick(&grid.neighbors(curr));
+            walk_next[idx(curr)] = Some(next);
+            curr = next;
+        }
+        let mut curr = start;
+        while !grid.visited[idx(curr)] {
+            let next = walk_next[idx(curr)].unwrap();
+            grid.carve(m, curr, next);
+            grid.visit(m, curr);
+            curr = next;
+        }
+    }
+}
+
+/// binary_tree links every cell to its north or east neighbor, giving a diagonal bias
+/// & long corridors along the north and east edges
+fn binary_tree(grid: &Grid, m: &mut Map) {
+    for c in grid.cells() {
+        grid.fill(m, c);
+        let options: Vec<(i32, i32)> = [(c.0, c.1 - 1), (c.0 + 1, c.1)]
+            .into_iter()
+            .filter(|&n| grid.is_open(n))
+            .collect();
+        if !options.is_empty() {
+            grid.carve(m, c, pick(&options));
+        }
+    }
+}
+
+/// hunt_and_kill walks randomly until stuck, then hunts for an unvisited cell
+/// next to the maze to continue from
+fn hunt_and_kill(grid: &mut Grid, m: &mut Map) {
+    let cells = grid.cells();
+    let mut curr = match cells.first() {
+        Some(&c) => c,
+        None => return,
+    };
+    grid.visit(m, curr);
+    loop {
+        let unvisited: Vec<(i32, i32)> = grid
+            .neighbors(curr)
+            .into_iter()
+            .filter(|&n| !grid.visited[idx(n)])
+            .collect();
+        if !unvisited.is_empty() {
+            let next = pick(&unvisited);
+            grid.carve(m, curr, next);
+            grid.visit(m, next);
+            curr = next;
+            continue;
+        }
+
+        // hunt row by row, an area without visited cells is started fresh
+        let hunted = cells.iter().copied().find(|&c| {
+            !grid.visited[idx(c)] && grid.neighbors(c).iter().any(|&n| grid.visited[idx(n)])
+        });
+        match hunted {
+            Some(c) => {
+                let visited: Vec<(i32, i32)> = grid
+                    .neighbors(c)
+                    .into_iter()
+                    .filter(|&n| grid.visited[idx(n)])
+                    .collect();
+                grid.carve(m, c, pick(&visited));
+                grid.visit(m, c);
+                curr = c;
+            }
+            None => match cells.iter().copied().find(|&c| !grid.visited[idx(c)]) {
+                Some(c) => {
+                    grid.visit(m, c);
+                    curr = c;
+                }
+                None => return,
+            },
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Cell {
+    x: i32,
+    y: i32,
+}
+
+impl Cell {
+    fn new(x: i32, y: i32, visited: &mut Map, visited_positions: &mut Vec<(i32, i32)>) -> Self {
+        visited[y as usize][x as usize] = TileType::Floor as i32;
+        visited_positions.push((x, y));
+        Self { x, y }
+    }
+
+    fn step(&mut self, m: &mut Map, v: &mut Map, visited_positions: &mut Vec<(i32, i32)>) {
+        let neighbors = get_neighbors(self.x, self.y, m);
+        let mut valid_neighbors: Vec<Cell> = Vec::new();
+        if let Some(n) = neighbors.north {
+            valid_neighbors.push(n);
+        }
+        if let Some(e) = neighbors.east {
+            valid_neighbors.push(e);
+        }
+        if let Some(s) = neighbors.south {
+            valid_neighbors.push(s);
+        }
+        if let Some(w) = neighbors.west {
+            valid_neighbors.push(w);
+        }
+        // backtrack if no valid neighbors
+        if valid_neighbors.is_empty() {
+            if visited_positions.is_empty() {
+                return;
+            }
+            (self.x, self.y) = visited_positions.pop().unwrap();
+            return;
+        }
+
+        let next = valid_neighbors[randr(0..valid_neighbors.len() as i32) as usize];
+        (self.x, self.y) = (next.x, next.y);
+        v[self.y as usize][self.x as usize] = TileType::Floor as i32;
+        visited_positions.push((self.x, self.y));
+        m[self.y as usize][self.x as usize] = TileType::Floor as i32;
+    }
+}
+
+struct Neighbors {
+    north: Option<Cell>,
+    east: Option<Cell>,
+    south: Option<Cell>,
+    west: Option<Cell>,
+}
+
+fn can_grow_tunnel(x: i32, y: i32, v: &Map) -> bool {
+    if in_bounds(x, y) && v[y as usize][x as usize] == TileType::Wall as i32 {
+        return true;
+    }
+    false
+}
+
+fn get_neighbors(x: i32, y: i32, m: &Map) -> Neighbors {
+    let mut n: Option<Cell> = None;
+    let mut e: Option<Cell> = None;
+    let mut s: Option<Cell> = None;
+    let mut w: Option<Cell> = None;
+
+    if can_grow_tunnel(x, y - 1, m)
+        && can_grow_tunnel(x, y - 2, m)
+        && can_grow_tunnel(x - 1, y - 1, m)
+        && can_grow_tunnel(x - 1, y - 2, m)
+        && can_grow_tunnel(x + 1, y - 1, m)
+        && can_grow_tunnel(x + 1, y - 2, m)
+    {
+        n = Some(Cell { x, y: y - 1 });
+    }
+    if can_grow_tunnel(x + 1, y, m)
+        && can_grow_tunnel(x + 2, y, m)
+        && can_grow_tunnel(x + 1, y - 1, m)
+        && can_grow_tunnel(x + 2, y - 1, m)
+        && can_grow_tunnel(x + 1, y + 1, m)
+        && can_grow_tunnel(x + 2, y + 1, m)
+    {
+        e = Some(Cell { x: x + 1, y });
+    }
+    if can_grow_tunnel(x, y + 1, m)
+        && can_grow_tunnel(x, y + 2, m)
+        && can_grow_tunnel(x - 1, y + 1, m)
+        && can_grow_tunnel(x - 1, y + 2, m)
+        && can_grow_tunnel(x + 1, y + 1, m)
+        && can_grow_tunnel(x + 1, y + 2, m)
+    {
+        s = Some(Cell { x, y: y + 1 });
+    }
+    if can_grow_tunnel(x - 1, y, m)
+        && can_grow_tunnel(x - 2, y, m)
+        && can_grow_tunnel(x - 2, y - 1, m)
+        && can_grow_tunnel(x - 1, y - 1, m)
+        && can_grow_tunnel(x - 2, y + 1, m)
+        && can_grow_tunnel(x - 1, y + 1, m)
+    {
+        w = Some(Cell { x: x - 1, y });
+    }
+
+    Neighbors {
+        north: n,
+        east: e,
+        south: s,
+        west: w,
+    }
+}
+
+fn backtrack(m: &mut Map) {
+    let mut visited = new_map(TileType::Wall);
+    let mut visited_positions: Vec<(i32, i32)> = Vec::new();
+
+    // pick a random wall location
+    let mut startx = COLS / 2;
+    let mut starty = ROWS / 2;
+    while m[starty as usize][startx as usize] != TileType::Wall as i32 {
+        startx = randr(0..COLS);
+        starty = randr(0..ROWS);
+    }
+
+    let mut c = Cell::new(startx, starty, &mut visited, &mut visited_positions);
+
+    while !visited_positions.is_empty() {
+        c.step(m, &mut visited, &mut visited_positions);
+    }
+
+    for y in 0..ROWS {
+        for x in 0..COLS {
+            if visited[y as usize][x as usize] == TileType::Floor as i32 {
+                m[y as usize][x as usize] = TileType::Floor as i32;
+            }
+        }
+    }
+}
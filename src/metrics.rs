@@ -0,0 +1,234 @@
+use crate::{fundamentals::*, maptools::*};
+use std::collections::VecDeque;
+
+/// MapMetrics summarizes the layout of a map, so algorithms & parameters can be compared by numbers
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MapMetrics {
+    /// share of floor tiles on the whole map (0.0 - 1.0)
+    pub floor_ratio: f32,
+    /// number of orthogonally connected floor areas
+    pub regions: i32,
+    /// number of open areas, i.e. connected floor tiles covered by at least one 3x3 floor block
+    pub rooms: i32,
+    /// number of floor tiles which don't belong to any room
+    pub corridor_length: i32,
+    /// number of floor tiles with exactly one orthogonal floor neighbor
+    pub dead_ends: i32,
+    /// cyclomatic number of the floor graph, without the trivial cycles of 2x2 floor blocks
+    pub loops: i32,
+    /// average shortest walking distance between two reachable floor tiles, sampled
+    pub avg_walk_distance: f32,
+    /// longest shortest walking distance between two reachable floor tiles, exact for mazes,
+    /// a close lower bound otherwise
+    pub max_walk_distance: i32,
+    /// number of floor tiles which disconnect their region when walled up
+    pub chokepoints: i32,
+}
+
+const SIZE: usize = (COLS * ROWS) as usize;
+/// start tiles the average walking distance is sampled from
+const WALK_SAMPLES: usize = 64;
+
+fn idx(x: i32, y: i32) -> usize {
+    (y * COLS + x) as usize
+}
+
+fn floor_neighbors(m: &Map, x: i32, y: i32) -> impl Iterator<Item = (i32, i32)> + '_ {
+    [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+        .into_iter()
+        .filter(move |&(nx, ny)| is_floor(m, nx, ny))
+}
+
+impl MapMetrics {
+    pub fn from_map(m: &Map) -> Self {
+        let mut num_floor = 0;
+        let mut num_edges = 0;
+        let mut num_squares = 0;
+        let mut dead_ends = 0;
+        for y in 0..ROWS {
+            for x in 0..COLS {
+                if !is_floor(m, x, y) {
+                    continue;
+                }
+                num_floor += 1;
+                if is_floor(m, x + 1, y) {
+                    num_edges += 1;
+                }
+                if is_floor(m, x, y + 1) {
+                    num_edges += 1;
+                }
+                if is_floor(m, x + 1, y) && is_floor(m, x, y + 1) && is_floor(m, x + 1, y + 1) {
+                    num_squares += 1;
+                }
+                if floor_neighbors(m, x, y).count() == 1 {
+                    dead_ends += 1;
+                }
+            }
+        }
+
+        let (_, regions) = label_regions(m);
+        let (rooms, room_tiles) = count_rooms(m);
+        let (avg_walk_distance, max_walk_distance) = walk_distances(m);
+
+        Self {
+            floor_ratio: num_floor as f32 / SIZE as f32,
+            regions,
+            rooms,
+            corridor_length: num_floor - room_tiles,
+            dead_ends,
+            // Euler's formula for 4-connected tile sets: holes = edges - vertices + components - 2x2 blocks
+            loops: num_edges - num_floor + regions - num_squares,
+            avg_walk_distance,
+            max_walk_distance,
+            chokepoints: count_chokepoints(m),
+        }
+    }
+}
+
+/// count_rooms returns the number of rooms and the number of tiles belonging to them
+fn count_rooms(m: &Map) -> (i32, i32) {
+    // mark every tile covered by a 3x3 block of floors
+    let mut room_tiles = new_map(TileType::Wall);
+    for y in 1..ROWS - 1 {
+        for x in 1..COLS - 1 {
+            let open = (-1..2).all(|j| (-1..2).all(|i| is_floor(m, x + i, y + j)));
+            if open {
+                for j in -1..2 {
+                    for i in -1..2 {
+                        room_tiles[(y + j) as usize][(x + i) as usize] = TileType::Floor as i32;
+                    }
+                }
+            }
+        }
+    }
+    let num_tiles = room_tiles.iter().flat_map(|r| r.iter()).sum();
+    let (_, num_rooms) = label_regions(&room_tiles);
+    (num_rooms, num_tiles)
+}
+
+/// bfs fills dist with the walking distances from (x, y), -1 for unreached tiles,
+/// returns the farthest tile, its distance & the sum of all distances
+fn bfs(m: &Map, x: i32, y: i32, dist: &mut [i32]) -> ((i32, i32), i32, u64) {
+    let mut queue: VecDeque<(i32, i32)> = VecDeque::new();
+    dist.iter_mut().for_each(|d| *d = -1);
+    dist[idx(x, y)] = 0;
+    queue.push_back((x, y));
+    let mut farthest = ((x, y), 0);
+    let mut sum: u64 = 0;
+    while let Some((cx, cy)) = queue.pop_front() {
+        let d = dist[idx(cx, cy)];
+        sum += d as u64;
+        if d > farthest.1 {
+            farthest = ((cx, cy), d);
+        }
+        for (nx, ny) in floor_neighbors(m, cx, cy) {
+            if dist[idx(nx, ny)] == -1 {
+                dist[idx(nx, ny)] = d + 1;
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+    (farthest.0, farthest.1, sum)
+}
+
+/// walk_distances estimates the average & maximum distance without a BFS from every tile:
+/// the average is taken over BFS runs from up to WALK_SAMPLES evenly spread floor tiles,
+/// the maximum is found by a double sweep (BFS to the farthest tile, then BFS from there)
+/// in every region, which is exact for tree-like layouts such as mazes
+fn walk_distances(m: &Map) -> (f32, i32) {
+    let mut dist = vec![-1; SIZE];
+    let mut sum: u64 = 0;
+    let mut pairs: u64 = 0;
+    let mut max = 0;
+
+    let floors: Vec<(i32, i32)> = (0..ROWS)
+        .flat_map(|y| (0..COLS).map(move |x| (x, y)))
+        .filter(|&(x, y)| is_floor(m, x, y))
+        .collect();
+    let step = (floors.len() / WALK_SAMPLES).max(1);
+    for &(x, y) in floors.iter().step_by(step) {
+        let (_, farthest, total) = bfs(m, x, y, &mut dist);
+        sum += total;
+        pairs += dist.iter().filter(|&&d| d > 0).count() as u64;
+        max = max.max(farthest);
+    }
+
+    let (labels, num_regions) = label_regions(m);
+    let mut swept = vec![false; num_regions as usize + 1];
+    for &(x, y) in &floors {
+        let region = labels[y as usize][x as usize] as usize;
+        if swept[region] {
+            continue;
+        }
+        swept[region] = true;
+        let ((fx, fy), _, _) = bfs(m, x, y, &mut dist);
+        let (_, farthest, _) = bfs(m, fx, fy, &mut dist);
+        max = max.max(farthest);
+    }
+
+    if pairs == 0 {
+        return (0.0, 0);
+    }
+    (sum as f32 / pairs as f32, max)
+}
+
+/// count_chokepoints finds articulation points of the floor graph with an iterative Tarjan DFS
+fn count_chokepoints(m: &Map) -> i32 {
+    let mut disc = vec![0; SIZE];
+    let mut low = vec![0; SIZE];
+    let mut is_cut = vec![false; SIZE];
+    let mut time = 0;
+    // stack of (x, y, parent index, index of the next neighbor to visit)
+    let mut stack: Vec<(i32, i32, usize, usize)> = Vec::new();
+
+    for y in 0..ROWS {
+        for x in 0..COLS {
+            if !is_floor(m, x, y) || disc[idx(x, y)] != 0 {
+                continue;
+            }
+            let root = idx(x, y);
+            let mut root_children = 0;
+            time += 1;
+            disc[root] = time;
+            low[root] = time;
+            stack.push((x, y, usize::MAX, 0));
+
+            while let Some(&mut (cx, cy, parent, ref mut next)) = stack.last_mut() {
+                let curr = idx(cx, cy);
+                let neighbor = floor_neighbors(m, cx, cy).nth(*next);
+                *next += 1;
+                match neighbor {
+                    Some((nx, ny)) => {
+                        let n = idx(nx, ny);
+                        if disc[n] == 0 {
+                            time += 1;
+                            disc[n] = time;
+                            low[n] = time;
+                            if curr == root {
+                                root_children += 1;
+                            }
+                            stack.push((nx, ny, curr, 0));
+                        } else if n != parent {
+                            low[curr] = low[curr].min(disc[n]);
+                        }
+                    }
+                    None => {
+                        stack.pop();
+                        if parent != usize::MAX {
+                            low[parent] = low[parent].min(low[curr]);
+                            if parent != root && low[curr] >= disc[parent] {
+                                is_cut[parent] = true;
+                            }
+                        }
+                    }
+                }
+            }
+
+            if root_children > 1 {
+                is_cut[root] = true;
+            }
+        }
+    }
+
+    is_cut.iter().filter(|&&c| c).count() as i32
+}
@@ -0,0 +1,157 @@
+use crate::{fundamentals::*, maptools::*, utils::*};
+
+/// DeadEndRemoval selects how remove_dead_ends deals with dead end corridors
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DeadEndRemoval {
+    /// keep filling dead ends until none are left
+    Full,
+    /// fill only the last tile of every dead end, the given number of times
+    Passes(i32),
+    /// fill the given percentage (0-100) of dead end corridors, chosen at random
+    Partial(i32),
+    /// fill random dead end corridors until at most the given number remains
+    KeepCount(i32),
+    /// punch dead ends through walls (at most max_distance tiles) to nearby floor, making loops
+    Loops { max_distance: i32 },
+}
+
+/// DeadEnd is a floor tile with a single floor neighbor, along with the
+/// length of the corridor leading to it from the nearest junction
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DeadEnd {
+    pub x: i32,
+    pub y: i32,
+    pub length: i32,
+}
+
+fn floor_neighbors(m: &Map, x: i32, y: i32) -> Vec<(i32, i32)> {
+    [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+        .into_iter()
+        .filter(|&(nx, ny)| is_floor(m, nx, ny))
+        .collect()
+}
+
+/// trace_corridor walks from a dead end until it reaches a junction or a room,
+/// returns the corridor tiles (junction excluded)
+fn trace_corridor(m: &Map, x: i32, y: i32) -> Vec<(i32, i32)> {
+    let mut corridor = vec![(x, y)];
+    let (mut prev, mut curr) = ((x, y), (x, y));
+    loop {
+        let next: Vec<(i32, i32)> = floor_neighbors(m, curr.0, curr.1)
+            .into_iter()
+            .filter(|&n| n != prev)
+            .collect();
+        if next.len() != 1 {
+            break;
+        }
+        let (nx, ny) = next[0];
+        if floor_neighbors(m, nx, ny).len() != 2 || corridor.contains(&(nx, ny)) {
+            break;
+        }
+        corridor.push((nx, ny));
+        prev = curr;
+        curr = (nx, ny);
+    }
+    corridor
+}
+
+pub fn find_dead_ends(m: &Map) -> Vec<DeadEnd> {
+    let mut dead_ends: Vec<DeadEnd> = Vec::new();
+    for y in 0..ROWS {
+        for x in 0..COLS {
+            if is_floor(m, x, y) && floor_neighbors(m, x, y).len() == 1 {
+                dead_ends.push(DeadEnd {
+                    x,
+                    y,
+                    length: trace_corridor(m, x, y).len() as i32,
+                });
+            }
+        }
+    }
+    dead_ends
+}
+
+fn fill_corridor(m: &mut Map, d: &DeadEnd) {
+    // the corridor may have been filled already from its other end
+    if !is_floor(m, d.x, d.y) {
+        return;
+    }
+    for (x, y) in trace_corridor(m, d.x, d.y) {
+        m[y as usize][x as usize] = TileType::Wall as i32;
+    }
+}
+
+fn fill_random_corridors(m: &mut Map, mut count: i32) {
+    let mut dead_ends = find_dead_ends(m);
+    while count > 0 && !dead_ends.is_empty() {
+        let d = dead_ends.swap_remove(randr(0..dead_ends.len() as i32) as usize);
+        fill_corridor(m, &d);
+        count -= 1;
+    }
+}
+
+/// punch_through tries to carve a straight tunnel from a dead end to the closest
+/// floor tile in front or beside it, returns true if it succeeded
+fn punch_through(m: &mut Map, d: &DeadEnd, max_distance: i32) -> bool {
+    let (px, py) = floor_neighbors(m, d.x, d.y)[0];
+    let (dx, dy) = (d.x - px, d.y - py);
+    // prefer going straight ahead, then the two sides
+    for (sx, sy) in [(dx, dy), (dy, dx), (-dy, -dx)] {
+        for dist in 1..=max_distance {
+            let (tx, ty) = (d.x + sx * dist, d.y + sy * dist);
+            // keep the map edges sealed
+            if !(1..COLS - 1).contains(&tx) || !(1..ROWS - 1).contains(&ty) {
+                break;
+            }
+            if is_floor(m, tx, ty) {
+                for i in 1..dist {
+                    m[(d.y + sy * i) as usize][(d.x + sx * i) as usize] = TileType::Floor as i32;
+                }
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// remove_dead_ends post-processes any map according to the selected mode
+pub fn remove_dead_ends(m: &mut Map, mode: DeadEndRemoval) {
+    match mode {
+        DeadEndRemoval::Full => loop {
+            let dead_ends = find_dead_ends(m);
+            if dead_ends.is_empty() {
+                break;
+            }
+            for d in &dead_ends {
+                fill_corridor(m, d);
+            }
+        },
+        DeadEndRemoval::Passes(n) => {
+            for _ in 0..n {
+                for d in find_dead_ends(m) {
+                    m[d.y as usize][d.x as usize] = TileType::Wall as i32;
+                }
+            }
+        }
+        DeadEndRemoval::Partial(percent) => {
+            let count = find_dead_ends(m).len() as i32 * percent.clamp(0, 100) / 100;
+            fill_random_corridors(m, count);
+        }
+        DeadEndRemoval::KeepCount(n) => loop {
+            // filling a corridor can turn its junction into a new dead end, so recount every time
+            let dead_ends = find_dead_ends(m);
+            if dead_ends.len() as i32 <= n.max(0) {
+                break;
+            }
+            fill_corridor(m, &dead_ends[randr(0..dead_ends.len() as i32) as usize]);
+        },
+        DeadEndRemoval::Loops { max_distance } => {
+            for d in find_dead_ends(m) {
+                // an earlier tunnel may have resolved this dead end already
+                if floor_neighbors(m, d.x, d.y).len() == 1 {
+                    punch_through(m, &d, max_distance);
+                }
+            }
+        }
+    }
+}
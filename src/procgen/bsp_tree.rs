@@ -1,158 +1,456 @@
+use super::DebugInfo;
+use crate::connections::{plan_connections, ConnectionParams, ConnectionStrategy};
+use crate::corridor::{carve_corridor, CorridorParams};
 use crate::{fundamentals::*, maptools::*, utils::*};
+use serde::Serialize;
 
+/// NodeId indexes a node in its BSPTree
+pub type NodeId = usize;
+
+/// BSPNode is an area of the map, either split in two children or a leaf holding a room
 #[derive(Clone, Debug)]
-struct BSPNode {
-    x: i32,
-    y: i32,
-    w: i32,
-    h: i32,
-    room: Option<Room>,
-    left_child: Option<Box<BSPNode>>,
-    right_child: Option<Box<BSPNode>>,
+pub struct BSPNode {
+    pub rect: Rect,
+    /// depth in the tree, 0 for the root
+    pub depth: i32,
+    pub parent: Option<NodeId>,
+    /// left & right child, None for leaves
+    pub children: Option<(NodeId, NodeId)>,
+    /// room carved in a leaf
+    pub room: Option<Room>,
+    /// corridor tiles connecting the rooms of the two children, empty for leaves
+    pub corridor: Vec<(i32, i32)>,
 }
 
 impl BSPNode {
-    fn new(
-        x: i32,
-        y: i32,
-        w: i32,
-        h: i32,
-        room: Option<Room>,
-        left: Option<Box<BSPNode>>,
-        right: Option<Box<BSPNode>>,
-    ) -> Self {
+    fn new(rect: Rect, depth: i32, parent: Option<NodeId>) -> Self {
         Self {
-            x,
-            y,
-            w,
-            h,
-            room,
-            left_child: left,
-            right_child: right,
+            rect,
+            depth,
+            parent,
+            children: None,
+            room: None,
+            corridor: Vec::new(),
         }
     }
 
-    fn split(&mut self) -> bool {
-        if self.left_child.is_some() && self.right_child.is_some() {
-            // node is already split
-            return false;
+    pub fn is_leaf(&self) -> bool {
+        self.children.is_none()
+    }
+}
+
+/// BSPTree is the partition hierarchy a BSP map was generated from
+#[derive(Clone, Debug)]
+pub struct BSPTree {
+    nodes: Vec<BSPNode>,
+}
+
+impl BSPTree {
+    fn new(rect: Rect) -> Self {
+        Self {
+            nodes: vec![BSPNode::new(rect, 0, None)],
         }
+    }
+
+    pub fn root(&self) -> NodeId {
+        0
+    }
+
+    pub fn node(&self, id: NodeId) -> &BSPNode {
+        &self.nodes[id]
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id].parent
+    }
+
+    pub fn children(&self, id: NodeId) -> Option<(NodeId, NodeId)> {
+        self.nodes[id].children
+    }
 
-        let mut hsplit = true;
-        let ratio = self.w as f32 / self.h as f32;
-        if ratio > 1.25 {
-            hsplit = false;
+    /// sibling returns the other child of the node's parent, None for the root
+    pub fn sibling(&self, id: NodeId) -> Option<NodeId> {
+        let (left, right) = self.children(self.parent(id)?)?;
+        Some(if left == id { right } else { left })
+    }
+
+    /// iter traverses the tree depth first, parents before children & left before right
+    pub fn iter(&self) -> Preorder<'_> {
+        Preorder {
+            tree: self,
+            stack: vec![self.root()],
         }
+    }
 
-        let max = match hsplit {
-            true => self.h - BSPTREE_LEAF_MIN_SIZE,
-            false => self.w - BSPTREE_LEAF_MIN_SIZE,
-        };
+    /// leaves iterates the leaf nodes from left to right
+    pub fn leaves(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.iter().filter(|&id| self.nodes[id].is_leaf())
+    }
 
-        if max <= BSPTREE_LEAF_MIN_SIZE {
-            // node too small to split further
-            return false;
+    /// find_room returns the room of the first leaf in the node's subtree
+    pub fn find_room(&self, id: NodeId) -> Option<Room> {
+        let node = &self.nodes[id];
+        if node.room.is_some() {
+            return node.room;
         }
+        let (left, right) = node.children?;
+        self.find_room(left).or_else(|| self.find_room(right))
+    }
 
-        let split = randr(BSPTREE_LEAF_MIN_SIZE..max);
-
-        if hsplit {
-            self.left_child = Some(Box::new(BSPNode::new(
-                self.x, self.y, self.w, split, None, None, None,
-            )));
-            self.right_child = Some(Box::new(BSPNode::new(
-                self.x,
-                self.y + split,
-                self.w,
-                self.h - split,
-                None,
-                None,
-                None,
-            )));
-        } else {
-            self.left_child = Some(Box::new(BSPNode::new(
-                self.x, self.y, split, self.h, None, None, None,
-            )));
-            self.right_child = Some(Box::new(BSPNode::new(
-                self.x + split,
-                self.y,
-                self.w - split,
-                self.h,
-                None,
-                None,
-                None,
-            )));
+    /// common_ancestor returns the deepest node both nodes descend from
+    pub fn common_ancestor(&self, a: NodeId, b: NodeId) -> NodeId {
+        let (mut a, mut b) = (a, b);
+        while self.nodes[a].depth > self.nodes[b].depth {
+            a = self.nodes[a].parent.unwrap();
+        }
+        while self.nodes[b].depth > self.nodes[a].depth {
+            b = self.nodes[b].parent.unwrap();
         }
+        while a != b {
+            a = self.nodes[a].parent.unwrap();
+            b = self.nodes[b].parent.unwrap();
+        }
+        a
+    }
 
-        true
+    /// subtree_rooms returns the rooms of all leaves in the node's subtree
+    pub fn subtree_rooms(&self, id: NodeId) -> Vec<Room> {
+        self.iter_from(id)
+            .filter_map(|leaf| self.nodes[leaf].room)
+            .collect()
     }
-}
 
-fn carve_leafs(curr: &mut BSPNode, map: &mut Map) {
-    if curr.left_child.is_some() || curr.right_child.is_some() {
-        if curr.left_child.is_some() {
-            carve_leafs(curr.left_child.as_mut().unwrap(), map);
+    /// iter_from traverses the subtree of the given node depth first
+    pub fn iter_from(&self, id: NodeId) -> Preorder<'_> {
+        Preorder {
+            tree: self,
+            stack: vec![id],
         }
-        if curr.right_child.is_some() {
-            carve_leafs(curr.right_child.as_mut().unwrap(), map);
+    }
+
+    /// split divides a leaf in two, returns false if it's too small or deep enough
+    fn split(&mut self, id: NodeId, params: &BSPTreeParams) -> bool {
+        let node = &self.nodes[id];
+        if !node.is_leaf() {
+            // node is already split
+            return false;
         }
-        if let (Some(l), Some(r)) = (curr.left_child.as_mut(), curr.right_child.as_mut()) {
-            let lroom = get_room(l).unwrap();
-            let rroom = get_room(r).unwrap();
+        if node.depth >= params.max_depth {
+            return false;
+        }
+        let Rect { x, y, w, h } = node.rect;
+        let min = params.leaf_min_size;
 
-            let (lx, ly) = lroom.center();
-            let (rx, ry) = rroom.center();
+        // hsplit cuts the node with a horizontal line, into a top & bottom child
+        let ratio = params.split_ratio as f32 / 100.0;
+        let (mut hsplit, forced) = match params.split_strategy {
+            SplitStrategy::Random => (randr(0..2) == 0, false),
+            // the root is wider than tall, so start with a vertical cut
+            SplitStrategy::Alternating => (node.depth % 2 == 1, false),
+            SplitStrategy::Aspect if w as f32 / h as f32 > ratio => (false, true),
+            SplitStrategy::Aspect if h as f32 / w as f32 > ratio => (true, true),
+            SplitStrategy::Aspect => (randr(0..2) == 0, false),
+        };
 
-            if randr(0..1) == 1 {
-                carve_horz_tunnel(map, lx, rx, ly);
-                carve_vert_tunnel(map, ly, ry, rx);
-            } else {
-                carve_vert_tunnel(map, ly, ry, lx);
-                carve_horz_tunnel(map, lx, rx, ry);
+        let size = if hsplit { h } else { w };
+        if size - min <= min {
+            // too small to split this way, try the other one unless the aspect ratio decided
+            let other = if hsplit { w } else { h };
+            if forced || other - min <= min {
+                return false;
             }
+            hsplit = !hsplit;
         }
-    } else {
-        if curr.room.is_none() {
-            let w = randr(BSPTREE_ROOM_MIN_SIZE..std::cmp::min(BSPTREE_ROOM_MAX_SIZE, curr.w - 1));
-            let h = randr(BSPTREE_ROOM_MIN_SIZE..std::cmp::min(BSPTREE_ROOM_MAX_SIZE, curr.h - 1));
-            let x = randr(curr.x..curr.x + (curr.w - 1) - w);
-            let y = randr(curr.y..curr.y + (curr.h - 1) - h);
 
-            curr.room = Some(Room::new(x, y, w, h));
-            curr.room.unwrap().carve(map);
+        let size = if hsplit { h } else { w };
+        let max = size - min;
+        let split = match params.split_position {
+            SplitPosition::Uniform => randr(min..max),
+            // average of two rolls, so splits near the middle are more likely
+            SplitPosition::Centered => (randr(min..max) + randr(min..max)) / 2,
+            SplitPosition::Middle => size / 2,
+        };
+
+        let (left, right) = if hsplit {
+            (
+                Rect { x, y, w, h: split },
+                Rect {
+                    x,
+                    y: y + split,
+                    w,
+                    h: h - split,
+                },
+            )
+        } else {
+            (
+                Rect { x, y, w: split, h },
+                Rect {
+                    x: x + split,
+                    y,
+                    w: w - split,
+                    h,
+                },
+            )
+        };
+
+        let depth = node.depth + 1;
+        let left_id = self.nodes.len();
+        self.nodes.push(BSPNode::new(left, depth, Some(id)));
+        self.nodes.push(BSPNode::new(right, depth, Some(id)));
+        self.nodes[id].children = Some((left_id, left_id + 1));
+        true
+    }
+}
+
+/// Preorder is the depth first iterator over a BSPTree's node ids
+pub struct Preorder<'a> {
+    tree: &'a BSPTree,
+    stack: Vec<NodeId>,
+}
+
+impl Iterator for Preorder<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.stack.pop()?;
+        if let Some((left, right)) = self.tree.children(id) {
+            self.stack.push(right);
+            self.stack.push(left);
         }
+        Some(id)
     }
 }
 
-fn get_room(curr: &mut BSPNode) -> Option<Room> {
-    if let Some(r) = curr.room {
-        return Some(r);
+/// span_points picks the coordinates two rooms connect at along one axis:
+/// a shared coordinate if the rooms overlap on it, the facing edges otherwise
+fn span_points(a1: i32, a2: i32, b1: i32, b2: i32) -> (i32, i32) {
+    let (lo, hi) = (a1.max(b1), a2.min(b2));
+    if lo < hi {
+        let shared = randr(lo..hi);
+        (shared, shared)
+    } else if a2 <= b1 {
+        (a2 - 1, b1)
+    } else {
+        (a1, b2 - 1)
     }
-    if curr.left_child.is_some() {
-        return get_room(curr.left_child.as_mut().unwrap());
+}
+
+/// nearest_edge_points returns the closest pair of rooms from both sides,
+/// along with the points on their facing edges to connect
+fn nearest_edge_points(lrooms: &[Room], rrooms: &[Room]) -> ((i32, i32), (i32, i32)) {
+    let gap = |a: &Room, b: &Room| {
+        let dx = (b.x1 - a.x2).max(a.x1 - b.x2).max(0);
+        let dy = (b.y1 - a.y2).max(a.y1 - b.y2).max(0);
+        dx + dy
+    };
+    let (a, b) = lrooms
+        .iter()
+        .flat_map(|a| rrooms.iter().map(move |b| (a, b)))
+        .min_by_key(|(a, b)| gap(a, b))
+        .unwrap();
+    let (ax, bx) = span_points(a.x1, a.x2, b.x1, b.x2);
+    let (ay, by) = span_points(a.y1, a.y2, b.y1, b.y2);
+    ((ax, ay), (bx, by))
+}
+
+fn carve_leafs(tree: &mut BSPTree, id: NodeId, map: &mut Map, params: &BSPTreeParams) {
+    if let Some((l, r)) = tree.children(id) {
+        carve_leafs(tree, l, map, params);
+        carve_leafs(tree, r, map, params);
+
+        let ((lx, ly), (rx, ry)) = match params.connection {
+            SiblingConnection::Centers => match (tree.find_room(l), tree.find_room(r)) {
+                (Some(lroom), Some(rroom)) => (lroom.center(), rroom.center()),
+                // nothing to connect with a side that was left empty
+                _ => return,
+            },
+            SiblingConnection::NearestEdges => {
+                let (lrooms, rrooms) = (tree.subtree_rooms(l), tree.subtree_rooms(r));
+                if lrooms.is_empty() || rrooms.is_empty() {
+                    return;
+                }
+                nearest_edge_points(&lrooms, &rrooms)
+            }
+            // connected all at once after carving, see connect_planned
+            SiblingConnection::Planned => return,
+        };
+
+        tree.nodes[id].corridor = carve_corridor(map, (lx, ly), (rx, ry), &params.corridor);
+    } else {
+        let curr = &mut tree.nodes[id];
+        if curr.room.is_some() {
+            return;
+        }
+        // chances are only rolled when set, so default parameters keep their maps
+        if params.empty_leaf_chance > 0 && randr(0..100) < params.empty_leaf_chance {
+            return;
+        }
+        let Rect { x, y, w, h } = curr.rect;
+        let room = if params.filled_leaf_chance > 0 && randr(0..100) < params.filled_leaf_chance {
+            // keep a wall towards the next leaf & the map edge
+            let x2 = (x + w - 1).min(COLS - 1);
+            let y2 = (y + h - 1).min(ROWS - 1);
+            Room::new(x, y, x2 - x, y2 - y)
+        } else {
+            let room_w = randr(params.room_min_size..std::cmp::min(params.room_max_size, w - 1));
+            let room_h = randr(params.room_min_size..std::cmp::min(params.room_max_size, h - 1));
+            let room_x = randr(x..x + (w - 1) - room_w);
+            let room_y = randr(y..y + (h - 1) - room_h);
+            Room::new(room_x, room_y, room_w, room_h)
+        };
+        room.carve(map);
+        curr.room = Some(room);
     }
-    if curr.right_child.is_some() {
-        return get_room(curr.right_child.as_mut().unwrap());
+}
+
+/// connect_planned connects the leaf rooms with the connection planner instead of along the tree,
+/// each corridor is stored on the deepest node containing both of its rooms
+fn connect_planned(tree: &mut BSPTree, map: &mut Map, params: &BSPTreeParams) {
+    let leaves: Vec<NodeId> = tree
+        .leaves()
+        .filter(|&id| tree.node(id).room.is_some())
+        .collect();
+    let centers: Vec<(i32, i32)> = leaves
+        .iter()
+        .map(|&id| tree.node(id).room.unwrap().center())
+        .collect();
+    for (a, b) in plan_connections(&centers, &params.connections) {
+        let tiles = carve_corridor(map, centers[a], centers[b], &params.corridor);
+        let ancestor = tree.common_ancestor(leaves[a], leaves[b]);
+        tree.nodes[ancestor].corridor.extend(tiles);
     }
-    None
 }
 
-fn split_until_fail(curr: &mut BSPNode) {
-    if !curr.split() {
+fn split_until_fail(tree: &mut BSPTree, id: NodeId, params: &BSPTreeParams) {
+    if !tree.split(id, params) {
         return;
     }
-    split_until_fail(curr.left_child.as_mut().unwrap());
-    split_until_fail(curr.right_child.as_mut().unwrap());
+    let (left, right) = tree.children(id).unwrap();
+    split_until_fail(tree, left, params);
+    split_until_fail(tree, right, params);
+}
+
+/// SplitStrategy decides whether a node is cut horizontally or vertically
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum SplitStrategy {
+    Random,
+    /// alternate between vertical & horizontal cuts with every level of the tree
+    Alternating,
+    /// cut across the longer side of nodes beyond the split ratio, randomly otherwise
+    Aspect,
+}
+
+/// SplitPosition is the distribution of where along a node the cut is made
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum SplitPosition {
+    Uniform,
+    /// favor cuts near the middle
+    Centered,
+    /// always cut in half
+    Middle,
+}
+
+/// SiblingConnection decides where the corridor between two sibling subtrees runs
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum SiblingConnection {
+    /// between the centers of the first room on each side
+    Centers,
+    /// between the facing edges of the closest pair of rooms
+    NearestEdges,
+    /// ignore the tree, connect all rooms with the connection planner
+    Planned,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct BSPTreeParams {
+    pub leaf_min_size: i32,
+    pub room_min_size: i32,
+    pub room_max_size: i32,
+    pub split_strategy: SplitStrategy,
+    /// aspect ratio in percent beyond which SplitStrategy::Aspect cuts across the longer side
+    pub split_ratio: i32,
+    pub split_position: SplitPosition,
+    /// nodes at this depth are not split any further
+    pub max_depth: i32,
+    /// chance (0-100) to leave a leaf without a room
+    pub empty_leaf_chance: i32,
+    /// chance (0-100) for a leaf's room to fill the whole leaf
+    pub filled_leaf_chance: i32,
+    pub connection: SiblingConnection,
+    /// used with SiblingConnection::Planned
+    pub connections: ConnectionParams,
+    pub corridor: CorridorParams,
+}
+
+impl Default for BSPTreeParams {
+    fn default() -> Self {
+        Self {
+            leaf_min_size: BSPTREE_LEAF_MIN_SIZE,
+            room_min_size: BSPTREE_ROOM_MIN_SIZE,
+            room_max_size: BSPTREE_ROOM_MAX_SIZE,
+            split_strategy: SplitStrategy::Aspect,
+            split_ratio: 125,
+            split_position: SplitPosition::Uniform,
+            max_depth: 16,
+            empty_leaf_chance: 0,
+            filled_leaf_chance: 0,
+            connection: SiblingConnection::Centers,
+            connections: ConnectionParams {
+                strategy: ConnectionStrategy::SpanningTree,
+                ..Default::default()
+            },
+            corridor: CorridorParams::default(),
+        }
+    }
 }
 
 pub struct BSPTreeGenerator {}
 
 impl BSPTreeGenerator {
-    pub fn generate_map() -> Map {
+    pub fn generate_map(params: &BSPTreeParams) -> Map {
+        Self::generate_map_debug(params).0
+    }
+
+    pub fn generate_map_debug(params: &BSPTreeParams) -> (Map, DebugInfo) {
+        let (map, tree) = Self::generate_tree(params);
+        let debug = DebugInfo {
+            partitions: tree
+                .iter()
+                .map(|id| (tree.node(id).rect, tree.node(id).depth))
+                .collect(),
+            rooms: tree
+                .iter()
+                .filter_map(|id| tree.node(id).room)
+                .map(|r| r.rect())
+                .collect(),
+            ..Default::default()
+        };
+        (map, debug)
+    }
+
+    /// generate_tree returns the map along with the BSP tree it was generated from
+    pub fn generate_tree(params: &BSPTreeParams) -> (Map, BSPTree) {
         let mut map = new_map(TileType::Wall);
-        let mut root = BSPNode::new(1, 1, COLS, ROWS, None, None, None);
-        split_until_fail(&mut root);
-        carve_leafs(&mut root, &mut map);
-        map
+        let mut tree = BSPTree::new(Rect {
+            x: 1,
+            y: 1,
+            w: COLS,
+            h: ROWS,
+        });
+        let root = tree.root();
+        split_until_fail(&mut tree, root, params);
+        carve_leafs(&mut tree, root, &mut map, params);
+        if params.connection == SiblingConnection::Planned {
+            connect_planned(&mut tree, &mut map, params);
+        }
+        (map, tree)
     }
 }
@@ -1,128 +1,462 @@
+use crate::connections::spanning_tree_edges;
+use crate::corridor::{carve_corridor, CorridorParams};
 use crate::{fundamentals::*, maptools::*, utils::*};
+use serde::{Serialize, Serializer};
+use std::fmt;
 
-const DEATH_LIMIT: i32 = 3;
-const BIRTH_LIMIT: i32 = 4;
-
-fn randomize_map_seal_edges(m: &mut Map) {
+fn randomize_map_seal_edges(m: &mut Map, wall_fill: i32) {
     for y in 0..ROWS {
         for x in 0..COLS {
             if (x == 0) | (x == COLS - 1) | (y == 0) | (y == ROWS - 1) {
                 m[y as usize][x as usize] = TileType::Wall as i32;
             } else {
-                m[y as usize][x as usize] = match randr(0..100) {
-                    0..=32 => TileType::Wall as i32,
-                    _ => TileType::Floor as i32,
+                m[y as usize][x as usize] = if randr(0..100) < wall_fill {
+                    TileType::Wall as i32
+                } else {
+                    TileType::Floor as i32
                 };
             }
         }
     }
 }
 
-pub fn evolve_map(m: &mut Map) {
-    for y in 0..ROWS {
-        for x in 0..COLS {
-            let neighbor_count = count_alive_neighbors(m, x, y);
-            if m[y as usize][x as usize] == TileType::Wall as i32 {
-                if neighbor_count < DEATH_LIMIT {
-                    m[y as usize][x as usize] = TileType::Floor as i32
-                }
+/// upper bound for the neighborhood radius, so neighbor counts fit into Counts
+pub const MAX_RADIUS: i32 = 3;
+/// most phases a schedule can hold, room for carving plus several smoothing & cleanup
+/// passes while keeping the params Copy
+pub const MAX_PHASES: usize = 8;
+
+/// Counts is a set of neighbor counts (0-63), one bit per count
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Counts(pub u64);
+
+impl Counts {
+    /// at_least holds every count from min up to max
+    pub fn at_least(min: i32, max: i32) -> Self {
+        let mut counts = Counts(0);
+        for n in min.max(0)..=max.min(63) {
+            counts.0 |= 1 << n;
+        }
+        counts
+    }
+
+    pub fn contains(self, n: i32) -> bool {
+        (0..64).contains(&n) && self.0 & (1 << n) != 0
+    }
+
+    /// min returns the lowest count in the set
+    pub fn min(self) -> Option<i32> {
+        if self.0 == 0 {
+            None
+        } else {
+            Some(self.0.trailing_zeros() as i32)
+        }
+    }
+}
+
+/// counts are written as digits, or separated by commas once any of them needs two digits
+impl fmt::Display for Counts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts: Vec<String> = (0..64)
+            .filter(|&n| self.contains(n))
+            .map(|n| n.to_string())
+            .collect();
+        let separator = if self.0 >> 10 == 0 { "" } else { "," };
+        write!(f, "{}", counts.join(separator))
+    }
+}
+
+impl Serialize for Counts {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum Neighborhood {
+    /// the square around the cell
+    Moore,
+    /// the diamond around the cell
+    VonNeumann,
+}
+
+/// CaRule is a rule in B/S notation, walls are the live cells.
+/// A floor becomes wall if its wall neighbor count is in birth,
+/// a wall stays wall if its count is in survival. Tiles outside the map count as walls.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct CaRule {
+    pub birth: Counts,
+    pub survival: Counts,
+    pub neighborhood: Neighborhood,
+    pub radius: i32,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RuleParseError {
+    /// a part that doesn't start with B or S
+    UnknownPart(String),
+    BadCount(String),
+    MissingPart(char),
+}
+
+impl fmt::Display for RuleParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleParseError::UnknownPart(p) => write!(f, "unknown rule part '{}'", p),
+            RuleParseError::BadCount(c) => write!(f, "bad neighbor count '{}'", c),
+            RuleParseError::MissingPart(c) => write!(f, "rule has no {} part", c),
+        }
+    }
+}
+
+impl std::error::Error for RuleParseError {}
+
+impl CaRule {
+    /// threshold is the usual cave rule, walls are born with at least birth_min wall neighbors
+    /// and survive with at least survival_min
+    pub fn threshold(
+        birth_min: i32,
+        survival_min: i32,
+        neighborhood: Neighborhood,
+        radius: i32,
+    ) -> Self {
+        let max = neighbor_offsets(neighborhood, radius).len() as i32;
+        Self {
+            birth: Counts::at_least(birth_min, max),
+            survival: Counts::at_least(survival_min, max),
+            neighborhood,
+            radius,
+        }
+    }
+
+    /// parse reads a rule like "B5678/S45678" for the given neighborhood & radius,
+    /// counts above 9 are separated by commas, as in "B13,14,15/S12,13,14,15"
+    pub fn parse(s: &str, neighborhood: Neighborhood, radius: i32) -> Result<Self, RuleParseError> {
+        let (mut birth, mut survival) = (None, None);
+        for part in s.split('/') {
+            let part = part.trim();
+            let target = match part.chars().next().map(|c| c.to_ascii_uppercase()) {
+                Some('B') => &mut birth,
+                Some('S') => &mut survival,
+                _ => return Err(RuleParseError::UnknownPart(part.to_owned())),
+            };
+            let counts = &part[1..];
+            let items: Vec<String> = if counts.contains(',') {
+                counts.split(',').map(|c| c.trim().to_owned()).collect()
             } else {
-                if BIRTH_LIMIT < neighbor_count {
-                    m[y as usize][x as usize] = TileType::Wall as i32
+                counts.chars().map(|c| c.to_string()).collect()
+            };
+            let mut set = Counts(0);
+            for item in items.iter().filter(|i| !i.is_empty()) {
+                match item.parse::<i32>() {
+                    Ok(n) if (0..64).contains(&n) => set.0 |= 1 << n,
+                    _ => return Err(RuleParseError::BadCount(item.clone())),
                 }
             }
+            *target = Some(set);
         }
+        Ok(Self {
+            birth: birth.ok_or(RuleParseError::MissingPart('B'))?,
+            survival: survival.ok_or(RuleParseError::MissingPart('S'))?,
+            neighborhood,
+            radius,
+        })
+    }
+
+    /// max_neighbors is the size of the rule's neighborhood
+    pub fn max_neighbors(&self) -> i32 {
+        neighbor_offsets(self.neighborhood, self.radius).len() as i32
     }
 }
 
-fn count_alive_neighbors(m: &Map, x: i32, y: i32) -> i32 {
-    let mut count = 0;
-    for i in -1..2 {
-        for j in -1..2 {
-            let nx = x + i;
-            let ny = y + j;
-            if (nx < 0) | (nx >= COLS) | (ny < 0) | (ny >= ROWS) {
-                count += 1;
-            } else if m[ny as usize][nx as usize] == TileType::Wall as i32 {
-                count += 1;
+impl fmt::Display for CaRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B{}/S{}", self.birth, self.survival)
+    }
+}
+
+/// CaPhase runs a rule for a number of iterations, phases with 0 iterations are skipped
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct CaPhase {
+    pub rule: CaRule,
+    pub iterations: i32,
+}
+
+/// CaSchedule is a fixed-capacity list of phases that run one after the other
+#[derive(Copy, Clone, Debug)]
+pub struct CaSchedule {
+    phases: [CaPhase; MAX_PHASES],
+    /// number of phases in use, the slots past it keep their phases for when it grows again
+    count: usize,
+}
+
+impl CaSchedule {
+    /// new returns a schedule of the given phases, which must not be empty. Phases beyond
+    /// MAX_PHASES are dropped, the unused slots repeat the last rule with 0 iterations.
+    pub fn new(phases: &[CaPhase]) -> Self {
+        let count = phases.len().min(MAX_PHASES);
+        let last = CaPhase {
+            iterations: 0,
+            ..phases[count - 1]
+        };
+        let mut all = [last; MAX_PHASES];
+        all[..count].copy_from_slice(&phases[..count]);
+        Self { phases: all, count }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// set_count grows or shrinks the schedule, keeping between 1 and MAX_PHASES phases
+    pub fn set_count(&mut self, count: usize) {
+        self.count = count.clamp(1, MAX_PHASES);
+    }
+
+    pub fn as_slice(&self) -> &[CaPhase] {
+        &self.phases[..self.count]
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [CaPhase] {
+        &mut self.phases[..self.count]
+    }
+}
+
+impl PartialEq for CaSchedule {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Serialize for CaSchedule {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.as_slice())
+    }
+}
+
+fn neighbor_offsets(neighborhood: Neighborhood, radius: i32) -> Vec<(i32, i32)> {
+    let r = radius.clamp(1, MAX_RADIUS);
+    let mut offsets = Vec::new();
+    for dy in -r..=r {
+        for dx in -r..=r {
+            let inside = match neighborhood {
+                Neighborhood::Moore => true,
+                Neighborhood::VonNeumann => dx.abs() + dy.abs() <= r,
+            };
+            if inside && (dx, dy) != (0, 0) {
+                offsets.push((dx, dy));
             }
         }
     }
-    count
+    offsets
 }
-fn get_random_cave_size(m: &mut Map) -> i32 {
-    // get random floor starting position for DFS
-    let mut rx = randr(0..COLS);
-    let mut ry = randr(0..ROWS);
-    while m[ry as usize][rx as usize] != TileType::Floor as i32 {
-        rx = randr(0..COLS);
-        ry = randr(0..ROWS);
-    }
-    let mut visited = new_map(TileType::Wall);
-    dfs(rx, ry, &mut visited, m);
 
+/// evolve_map applies one step of the rule to every tile at once,
+/// reading from a copy of the map so updated tiles don't affect their neighbors
+pub fn evolve_map(m: &mut Map, rule: &CaRule) {
+    let offsets = neighbor_offsets(rule.neighborhood, rule.radius);
+    let prev = *m;
     for y in 0..ROWS {
         for x in 0..COLS {
-            if visited[y as usize][x as usize] == 1 {
-                m[y as usize][x as usize] = TileType::Floor as i32;
+            let walls = count_walls(&prev, x, y, &offsets);
+            let is_wall = prev[y as usize][x as usize] == TileType::Wall as i32;
+            let stays_wall = if is_wall {
+                rule.survival.contains(walls)
             } else {
-                m[y as usize][x as usize] = TileType::Wall as i32;
-            }
+                rule.birth.contains(walls)
+            };
+            m[y as usize][x as usize] = if stays_wall {
+                TileType::Wall as i32
+            } else {
+                TileType::Floor as i32
+            };
         }
     }
-
-    let num_floor: i32 = visited
-        .iter()
-        .flat_map(|x: &[i32; COLS as usize]| x.iter())
-        .sum();
-
-    num_floor
 }
 
-fn dfs(x: i32, y: i32, v: &mut Map, m: &Map) {
-    v[y as usize][x as usize] = TileType::Floor as i32;
-    if is_valid(x - 1, y, v, m) {
-        dfs(x - 1, y, v, m);
+fn count_walls(m: &Map, x: i32, y: i32, offsets: &[(i32, i32)]) -> i32 {
+    let mut count = 0;
+    for &(dx, dy) in offsets {
+        let (nx, ny) = (x + dx, y + dy);
+        if !in_bounds(nx, ny) || m[ny as usize][nx as usize] == TileType::Wall as i32 {
+            count += 1;
+        }
     }
-    if is_valid(x + 1, y, v, m) {
-        dfs(x + 1, y, v, m);
+    count
+}
+
+/// select_caves keeps the caves picked by selection & fills in the others,
+/// returns the number of floor tiles left
+fn select_caves(m: &mut Map, selection: CaveSelection) -> i32 {
+    let (labels, num_regions) = label_regions(m);
+    if num_regions == 0 {
+        return 0;
     }
-    if is_valid(x, y - 1, v, m) {
-        dfs(x, y - 1, v, m);
+    let mut sizes = vec![0; num_regions as usize + 1];
+    for row in labels.iter() {
+        for &label in row.iter() {
+            sizes[label as usize] += 1;
+        }
     }
-    if is_valid(x, y + 1, v, m) {
-        dfs(x, y + 1, v, m);
+
+    let keep = match selection {
+        // a random floor tile, so bigger caves are more likely to be picked
+        CaveSelection::Random => loop {
+            let label = labels[randr(0..ROWS) as usize][randr(0..COLS) as usize];
+            if label != 0 {
+                break Some(label);
+            }
+        },
+        CaveSelection::Largest => (1..=num_regions).max_by_key(|&l| sizes[l as usize]),
+        CaveSelection::Joined => {
+            join_caves(m, &labels, num_regions);
+            None
+        }
+    };
+    if let Some(keep) = keep {
+        for y in 0..ROWS as usize {
+            for x in 0..COLS as usize {
+                if labels[y][x] != keep {
+                    m[y][x] = TileType::Wall as i32;
+                }
+            }
+        }
     }
+    m.iter()
+        .flat_map(|r| r.iter())
+        .filter(|&&t| t == TileType::Floor as i32)
+        .count() as i32
 }
 
-fn is_valid(x: i32, y: i32, v: &Map, m: &Map) -> bool {
-    if (x < 0) | (x >= COLS) | (y < 0) | (y >= ROWS) {
-        return false;
+/// join_caves digs tunnels along the spanning tree of the caves,
+/// each cave is represented by its tile closest to its centroid
+fn join_caves(m: &mut Map, labels: &Map, num_regions: i32) {
+    let mut tiles: Vec<Vec<(i32, i32)>> = vec![Vec::new(); num_regions as usize];
+    for y in 0..ROWS {
+        for x in 0..COLS {
+            let label = labels[y as usize][x as usize];
+            if label != 0 {
+                tiles[label as usize - 1].push((x, y));
+            }
+        }
     }
-    if v[y as usize][x as usize] == TileType::Floor as i32 {
-        return false;
+    let points: Vec<(i32, i32)> = tiles
+        .iter()
+        .map(|cave| {
+            let n = cave.len() as i32;
+            let cx = cave.iter().map(|t| t.0).sum::<i32>() / n;
+            let cy = cave.iter().map(|t| t.1).sum::<i32>() / n;
+            *cave
+                .iter()
+                .min_by_key(|t| (t.0 - cx).abs() + (t.1 - cy).abs())
+                .unwrap()
+        })
+        .collect();
+    for (a, b) in spanning_tree_edges(&points) {
+        carve_corridor(m, points[a], points[b], &CorridorParams::default());
+    }
+}
+
+fn generate_caves(m: &mut Map, params: &CellularAutomataParams) {
+    randomize_map_seal_edges(m, params.wall_fill);
+    for phase in params.phases.as_slice() {
+        for _ in 0..phase.iterations {
+            evolve_map(m, &phase.rule);
+        }
     }
-    if m[y as usize][x as usize] == TileType::Wall as i32 {
-        return false;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum CaveSelection {
+    /// the cave under a random floor tile, the other caves are filled in
+    Random,
+    /// the biggest cave, the other caves are filled in
+    Largest,
+    /// every cave, joined by tunnels
+    Joined,
+}
+
+/// CaveError is returned when no attempt hit the target floor ratio
+#[derive(Debug)]
+pub struct CaveError {
+    pub attempts: i32,
+    /// the map that came closest to the target
+    pub closest: Box<Map>,
+}
+
+impl fmt::Display for CaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "no cave within the target floor ratio after {} attempts",
+            self.attempts
+        )
     }
-    true
 }
 
-fn generate_caves(m: &mut Map) {
-    randomize_map_seal_edges(m);
-    for _ in 0..15 {
-        evolve_map(m);
+impl std::error::Error for CaveError {}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct CellularAutomataParams {
+    /// percentage (0-100) of tiles that start out as walls
+    pub wall_fill: i32,
+    /// rules run one phase after the other, e.g. carving caves first & smoothing them after
+    pub phases: CaSchedule,
+    pub selection: CaveSelection,
+    /// percentage (0-100) of the map that has to be floor once the caves are selected
+    pub min_floor: i32,
+    pub max_floor: i32,
+    /// caves are generated again until they hit the floor ratio, at most this many times
+    pub max_attempts: i32,
+}
+
+impl Default for CellularAutomataParams {
+    fn default() -> Self {
+        Self {
+            wall_fill: 33,
+            phases: CaSchedule::new(&[CaPhase {
+                rule: CaRule::threshold(5, 2, Neighborhood::Moore, 1),
+                iterations: 15,
+            }]),
+            selection: CaveSelection::Random,
+            min_floor: 36,
+            max_floor: 100,
+            max_attempts: 100,
+        }
     }
 }
 
 pub struct CellularAutomataGenerator {}
 impl CellularAutomataGenerator {
-    pub fn generate_map() -> Map {
-        let mut map = new_map(TileType::Wall);
-        generate_caves(&mut map);
-        while get_random_cave_size(&mut map) < 1000 {
-            generate_caves(&mut map);
+    /// generate_map settles for the closest cave when the floor ratio can't be hit
+    pub fn generate_map(params: &CellularAutomataParams) -> Map {
+        Self::try_generate_map(params).unwrap_or_else(|e| *e.closest)
+    }
+
+    pub fn try_generate_map(params: &CellularAutomataParams) -> Result<Map, CaveError> {
+        let tiles = COLS * ROWS;
+        let (min, max) = (
+            params.min_floor * tiles / 100,
+            params.max_floor * tiles / 100,
+        );
+        let attempts = params.max_attempts.max(1);
+        let mut closest: Option<(i32, Map)> = None;
+        for _ in 0..attempts {
+            let mut map = new_map(TileType::Wall);
+            generate_caves(&mut map, params);
+            let floor = select_caves(&mut map, params.selection);
+            // how many tiles the floor is off the target range
+            let miss = (min - floor).max(floor - max).max(0);
+            if miss == 0 {
+                return Ok(map);
+            }
+            if closest.is_none_or(|(best, _)| miss < best) {
+                closest = Some((miss, map));
+            }
         }
-        map
+        Err(CaveError {
+            attempts,
+            closest: Box::new(closest.unwrap().1),
+        })
     }
 }
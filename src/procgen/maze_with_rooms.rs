@@ -1,165 +1,33 @@
+use super::DebugInfo;
+use crate::connections::{plan_connections, ConnectionParams};
+use crate::corridor::{carve_corridor, CorridorParams};
+use crate::maze::{carve_maze, MazeAlgorithm};
+use crate::postprocess::{remove_dead_ends, DeadEndRemoval};
 use crate::{fundamentals::*, maptools::*, utils::*};
+use serde::Serialize;
 
-#[derive(Copy, Clone, Debug)]
-struct Cell {
-    x: i32,
-    y: i32,
-}
-
-impl Cell {
-    fn new(x: i32, y: i32, visited: &mut Map, visited_positions: &mut Vec<Pos>) -> Self {
-        visited[y as usize][x as usize] = TileType::Floor as i32;
-        visited_positions.push(Pos { x, y });
-        Self { x, y }
-    }
-
-    fn step(&mut self, m: &mut Map, v: &mut Map, visited_positions: &mut Vec<Pos>) {
-        let neighbors = get_neighbors(self.x, self.y, m);
-        let mut valid_neighbors: Vec<Cell> = Vec::new();
-        if let Some(n) = neighbors.north {
-            valid_neighbors.push(n);
-        }
-        if let Some(e) = neighbors.east {
-            valid_neighbors.push(e);
-        }
-        if let Some(s) = neighbors.south {
-            valid_neighbors.push(s);
-        }
-        if let Some(w) = neighbors.west {
-            valid_neighbors.push(w);
-        }
-        // backtrack if no valid neighbors
-        if valid_neighbors.is_empty() {
-            if visited_positions.is_empty() {
-                return;
-            }
-            let prev_loc = visited_positions.pop().unwrap();
-            (self.x, self.y) = (prev_loc.x, prev_loc.y);
-            return;
-        }
+/// widest maze passages, so rooms snapped to the cells still fit the map
+const MAX_CORRIDOR_WIDTH: i32 = 4;
 
-        let next = valid_neighbors[randr(0..valid_neighbors.len() as i32) as usize];
-        (self.x, self.y) = (next.x, next.y);
-        v[self.y as usize][self.x as usize] = TileType::Floor as i32;
-        visited_positions.push(Pos {
-            x: self.x,
-            y: self.y,
-        });
-        m[self.y as usize][self.x as usize] = TileType::Floor as i32;
-    }
-}
-
-struct Neighbors {
-    north: Option<Cell>,
-    east: Option<Cell>,
-    south: Option<Cell>,
-    west: Option<Cell>,
-}
-
-fn can_grow_tunnel(x: i32, y: i32, v: &Map) -> bool {
-    if in_bounds(x, y) && v[y as usize][x as usize] == TileType::Wall as i32 {
-        return true;
-    }
-    false
-}
-
-fn get_neighbors(x: i32, y: i32, m: &Map) -> Neighbors {
-    let mut n: Option<Cell> = None;
-    let mut e: Option<Cell> = None;
-    let mut s: Option<Cell> = None;
-    let mut w: Option<Cell> = None;
-
-    if can_grow_tunnel(x, y - 1, m)
-        && can_grow_tunnel(x, y - 2, m)
-        && can_grow_tunnel(x - 1, y - 1, m)
-        && can_grow_tunnel(x - 1, y - 2, m)
-        && can_grow_tunnel(x + 1, y - 1, m)
-        && can_grow_tunnel(x + 1, y - 2, m)
-    {
-        n = Some(Cell { x, y: y - 1 });
-    }
-    if can_grow_tunnel(x + 1, y, m)
-        && can_grow_tunnel(x + 2, y, m)
-        && can_grow_tunnel(x + 1, y - 1, m)
-        && can_grow_tunnel(x + 2, y - 1, m)
-        && can_grow_tunnel(x + 1, y + 1, m)
-        && can_grow_tunnel(x + 2, y + 1, m)
-    {
-        e = Some(Cell { x: x + 1, y });
-    }
-    if can_grow_tunnel(x, y + 1, m)
-        && can_grow_tunnel(x, y + 2, m)
-        && can_grow_tunnel(x - 1, y + 1, m)
-        && can_grow_tunnel(x - 1, y + 2, m)
-        && can_grow_tunnel(x + 1, y + 1, m)
-        && can_grow_tunnel(x + 1, y + 2, m)
-    {
-        s = Some(Cell { x, y: y + 1 });
-    }
-    if can_grow_tunnel(x - 1, y, m)
-        && can_grow_tunnel(x - 2, y, m)
-        && can_grow_tunnel(x - 2, y - 1, m)
-        && can_grow_tunnel(x - 1, y - 1, m)
-        && can_grow_tunnel(x - 2, y + 1, m)
-        && can_grow_tunnel(x - 1, y + 1, m)
-    {
-        w = Some(Cell { x: x - 1, y });
-    }
-
-    Neighbors {
-        north: n,
-        east: e,
-        south: s,
-        west: w,
-    }
-}
-
-fn count_neighbors(p: &Pos, v: &Map) -> i32 {
-    let mut count = 0;
-    if in_bounds(p.x - 1, p.y) && v[p.y as usize][p.x as usize - 1] == TileType::Floor as i32 {
-        count = count + 1;
-    }
-    if in_bounds(p.x + 1, p.y) && v[p.y as usize][p.x as usize + 1] == TileType::Floor as i32 {
-        count = count + 1;
-    }
-    if in_bounds(p.x, p.y - 1) && v[p.y as usize - 1][p.x as usize] == TileType::Floor as i32 {
-        count = count + 1;
-    }
-    if in_bounds(p.x, p.y + 1) && v[p.y as usize + 1][p.x as usize] == TileType::Floor as i32 {
-        count = count + 1;
-    }
-    count
-}
-
-fn trim_dead_ends(m: &mut Map) {
-    // find dead ends - cells with exactly one neighbor
-    let dead_ends: Vec<Pos> = m
-        .iter()
-        .flat_map(|x: &[i32; COLS as usize]| x.iter())
-        .enumerate()
-        .filter(|(_, &d)| d == 1)
-        .map(|(idx, _)| {
-            let x = idx as i32 % COLS;
-            let y = idx as i32 / COLS;
-            Pos { x, y }
-        })
-        .filter(|p| count_neighbors(p, m) == 1)
-        .collect();
-    for d in dead_ends {
-        m[d.y as usize][d.x as usize] = TileType::Wall as i32;
-    }
-}
-
-fn place_rooms(m: &mut Map) -> Vec<Room> {
+/// place_rooms carves non-overlapping rooms, with a grid pitch given they are snapped to
+/// the maze cells so the room walls line up with the walls between cells
+fn place_rooms(m: &mut Map, room_attempts: i32, grid_pitch: Option<i32>) -> Vec<Room> {
     const ROOM_SIZE_MIN: i32 = 6;
     const ROOMS_SIZE_MAX: i32 = 16;
     let mut rooms: Vec<Room> = vec![];
 
-    for _ in 0..20 {
-        let w: i32 = randr(ROOM_SIZE_MIN..ROOMS_SIZE_MAX);
-        let h: i32 = randr(ROOM_SIZE_MIN..ROOMS_SIZE_MAX);
-        let x: i32 = randr(1..COLS - w);
-        let y: i32 = randr(1..ROWS - h);
+    for _ in 0..room_attempts {
+        let mut w: i32 = randr(ROOM_SIZE_MIN..ROOMS_SIZE_MAX);
+        let mut h: i32 = randr(ROOM_SIZE_MIN..ROOMS_SIZE_MAX);
+        let mut x: i32 = randr(1..COLS - w);
+        let mut y: i32 = randr(1..ROWS - h);
+        if let Some(pitch) = grid_pitch {
+            // a room spans at least one cell, so it can grow past its random size
+            w = ((w + 1) / pitch).max(1) * pitch - 1;
+            h = ((h + 1) / pitch).max(1) * pitch - 1;
+            x = 1 + (x.min(COLS - 1 - w) - 1) / pitch * pitch;
+            y = 1 + (y.min(ROWS - 1 - h) - 1) / pitch * pitch;
+        }
 
         let mut curr_room = Room::new(x, y, w, h);
 
@@ -183,155 +51,191 @@ fn place_rooms(m: &mut Map) -> Vec<Room> {
         }
     }
 
+    // shrink the room rects back after placement
+    for room in &mut rooms {
+        room.x1 += 3;
+        room.y1 += 3;
+        room.x2 -= 3;
+        room.y2 -= 3;
+    }
+
     rooms
 }
 
-fn connect_rooms(rooms: &mut Vec<Room>, m: &mut Map) {
-    for room in rooms {
-        // find possible connection points
-        // = walls of the room which have a tunnel next to them
-
-        // first shrink the room rect after placement
-        room.x1 = room.x1 + 3;
-        room.x2 = room.x2 - 3;
-        room.y1 = room.y1 + 3;
-        room.y2 = room.y2 - 3;
-
-        // find the room walls
-        let mut perimeter: Vec<Pos> = Vec::new();
-        for x in room.x1..room.x2 {
-            for y in room.y1..room.y2 {
-                if x == room.x1 || x == room.x2 - 1 || y == room.y1 || y == room.y2 - 1 {
-                    perimeter.push(Pos { x, y });
+/// connect_regions merges the rooms & maze passages through connectors, walls between two
+/// different regions. Starting from a random region, random connectors of the merged area are
+/// opened until no region is left apart, connectors that became redundant get opened with the
+/// extra connector chance to make loops.
+fn connect_regions(m: &mut Map, extra_connector_chance: i32) {
+    let (labels, num_regions) = label_regions(m);
+    if num_regions < 2 {
+        return;
+    }
+
+    // connector tiles & the two regions they lie between
+    let mut connectors: Vec<((i32, i32), (i32, i32))> = Vec::new();
+    for y in 1..ROWS - 1 {
+        for x in 1..COLS - 1 {
+            if m[y as usize][x as usize] != TileType::Wall as i32 {
+                continue;
+            }
+            for ((ax, ay), (bx, by)) in [((x - 1, y), (x + 1, y)), ((x, y - 1), (x, y + 1))] {
+                let a = labels[ay as usize][ax as usize];
+                let b = labels[by as usize][bx as usize];
+                if a != 0 && b != 0 && a != b {
+                    connectors.push(((x, y), (a, b)));
+                    break;
                 }
             }
         }
+    }
 
-        let mut possible_connection_points: Vec<Pos> = Vec::new();
-        for p in perimeter {
-            if in_bounds(p.x - 1, p.y)
-                && in_bounds(p.x - 2, p.y)
-                && m[p.y as usize][p.x as usize - 1] == TileType::Wall as i32
-                && m[p.y as usize][p.x as usize - 2] == TileType::Floor as i32
-            {
-                possible_connection_points.push(p);
+    // merged[r] is the region r has been merged into
+    let mut merged: Vec<i32> = (0..=num_regions).collect();
+    let main = randr(1..num_regions + 1);
+    let mut opened: Vec<(i32, i32)> = Vec::new();
+    loop {
+        let candidates: Vec<usize> = (0..connectors.len())
+            .filter(|&i| {
+                let (_, (a, b)) = connectors[i];
+                merged[a as usize] == main || merged[b as usize] == main
+            })
+            .collect();
+        if candidates.is_empty() {
+            break;
+        }
+        let ((x, y), (a, b)) = connectors[pick(&candidates)];
+        m[y as usize][x as usize] = TileType::Floor as i32;
+        opened.push((x, y));
+
+        let other = if merged[a as usize] == main {
+            merged[b as usize]
+        } else {
+            merged[a as usize]
+        };
+        for region in merged.iter_mut() {
+            if *region == other {
+                *region = main;
             }
-            if in_bounds(p.x + 1, p.y)
-                && in_bounds(p.x + 2, p.y)
-                && m[p.y as usize][p.x as usize + 1] == TileType::Wall as i32
-                && m[p.y as usize][p.x as usize + 2] == TileType::Floor as i32
-            {
-                possible_connection_points.push(p);
-            }
-            if in_bounds(p.x, p.y - 1)
-                && in_bounds(p.x, p.y - 2)
-                && m[p.y as usize - 1][p.x as usize] == TileType::Wall as i32
-                && m[p.y as usize - 2][p.x as usize] == TileType::Floor as i32
-            {
-                possible_connection_points.push(p);
+        }
+
+        connectors.retain(|&((x, y), (a, b))| {
+            if merged[a as usize] != merged[b as usize] {
+                return true;
             }
-            if in_bounds(p.x, p.y + 1)
-                && in_bounds(p.x, p.y + 2)
-                && m[p.y as usize + 1][p.x as usize] == TileType::Wall as i32
-                && m[p.y as usize + 2][p.x as usize] == TileType::Floor as i32
-            {
-                possible_connection_points.push(p);
+            // don't open extra connectors right next to another one
+            let crowded = opened
+                .iter()
+                .any(|&(ox, oy)| (ox - x).abs() + (oy - y).abs() < 2);
+            if !crowded && extra_connector_chance > 0 && randr(0..100) < extra_connector_chance {
+                m[y as usize][x as usize] = TileType::Floor as i32;
+                opened.push((x, y));
             }
-        }
+            false
+        });
+    }
 
-        if possible_connection_points.is_empty() {
-            return;
-        }
+    join_leftover_regions(m);
+}
 
-        let mut connection_points: Vec<Pos> = Vec::new();
-        for _ in 0..2 {
-            let connection_point = possible_connection_points
-                [randr(0..possible_connection_points.len() as i32) as usize];
-            connection_points.push(connection_point);
+/// join_leftover_regions digs corridors to regions no connector could reach,
+/// each from the closest tile of the area already joined
+fn join_leftover_regions(m: &mut Map) {
+    loop {
+        let (labels, num_regions) = label_regions(m);
+        if num_regions < 2 {
+            return;
         }
-
-        for p in connection_points {
-            if in_bounds(p.x - 1, p.y)
-                && in_bounds(p.x - 2, p.y)
-                && m[p.y as usize][p.x as usize - 1] == TileType::Wall as i32
-                && m[p.y as usize][p.x as usize - 2] == TileType::Floor as i32
-            {
-                m[p.y as usize][p.x as usize - 1] = TileType::Floor as i32;
-                continue;
-            }
-            if in_bounds(p.x + 1, p.y)
-                && in_bounds(p.x + 2, p.y)
-                && m[p.y as usize][p.x as usize + 1] == TileType::Wall as i32
-                && m[p.y as usize][p.x as usize + 2] == TileType::Floor as i32
-            {
-                m[p.y as usize][p.x as usize + 1] = TileType::Floor as i32;
-                continue;
-            }
-            if in_bounds(p.x, p.y - 1)
-                && in_bounds(p.x, p.y - 2)
-                && m[p.y as usize - 1][p.x as usize] == TileType::Wall as i32
-                && m[p.y as usize - 2][p.x as usize] == TileType::Floor as i32
-            {
-                m[p.y as usize - 1][p.x as usize] = TileType::Floor as i32;
-                continue;
-            }
-            if in_bounds(p.x, p.y + 1)
-                && in_bounds(p.x, p.y + 2)
-                && m[p.y as usize + 1][p.x as usize] == TileType::Wall as i32
-                && m[p.y as usize + 2][p.x as usize] == TileType::Floor as i32
-            {
-                m[p.y as usize + 1][p.x as usize] = TileType::Floor as i32;
-                continue;
+        let mut joined: Vec<(i32, i32)> = Vec::new();
+        let mut rest: Vec<(i32, i32)> = Vec::new();
+        for y in 0..ROWS {
+            for x in 0..COLS {
+                match labels[y as usize][x as usize] {
+                    0 => {}
+                    1 => joined.push((x, y)),
+                    _ => rest.push((x, y)),
+                }
             }
         }
+        let (from, to) = joined
+            .iter()
+            .flat_map(|&a| rest.iter().map(move |&b| (a, b)))
+            .min_by_key(|&(a, b)| (a.0 - b.0).abs() + (a.1 - b.1).abs())
+            .unwrap();
+        carve_corridor(m, from, to, &CorridorParams::default());
     }
 }
 
-#[derive(Copy, Clone, Debug)]
-struct Pos {
-    x: i32,
-    y: i32,
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct MazeParams {
+    /// with no room attempts the map is a pure maze
+    pub room_attempts: i32,
+    pub algorithm: MazeAlgorithm,
+    /// snap rooms & maze cells to the grid, giving clean grid aligned mazes
+    pub align_to_grid: bool,
+    /// width of the maze passages, up to 4
+    pub corridor_width: i32,
+    pub dead_end_passes: i32,
+    /// chance (0-100) to open a connector between regions that are already connected, for loops
+    pub extra_connector_chance: i32,
+    /// when set, rooms are also linked by corridors running straight between them
+    pub room_connections: Option<ConnectionParams>,
 }
 
-pub struct MazeGenerator {}
-impl MazeGenerator {
-    pub fn generate_map() -> Map {
-        let mut map = new_map(TileType::Wall);
-        let mut visited = new_map(TileType::Wall);
-        let mut visited_positions: Vec<Pos> = Vec::new();
-
-        let mut rooms = place_rooms(&mut map);
-
-        // pick a random wall location
-        let mut startx = COLS / 2;
-        let mut starty = ROWS / 2;
-        while map[starty as usize][startx as usize] != TileType::Wall as i32 {
-            startx = randr(0..COLS);
-            starty = randr(0..ROWS);
+impl Default for MazeParams {
+    fn default() -> Self {
+        Self {
+            room_attempts: 20,
+            algorithm: MazeAlgorithm::Backtracker,
+            align_to_grid: false,
+            corridor_width: 1,
+            dead_end_passes: 5,
+            extra_connector_chance: 2,
+            room_connections: None,
         }
+    }
+}
 
-        let mut c = Cell::new(startx, starty, &mut visited, &mut visited_positions);
+pub struct MazeGenerator {}
+impl MazeGenerator {
+    pub fn generate_map(params: &MazeParams) -> Map {
+        Self::generate_map_debug(params).0
+    }
 
-        while !visited_positions.is_empty() {
-            c.step(&mut map, &mut visited, &mut visited_positions);
-        }
+    pub fn generate_map_debug(params: &MazeParams) -> (Map, DebugInfo) {
+        let mut map = new_map(TileType::Wall);
+        let corridor_width = params.corridor_width.clamp(1, MAX_CORRIDOR_WIDTH);
+        let grid_pitch = params.align_to_grid.then_some(corridor_width + 1);
+        let rooms = place_rooms(&mut map, params.room_attempts, grid_pitch);
 
-        for y in 0..ROWS {
-            for x in 0..COLS {
-                if visited[y as usize][x as usize] == TileType::Floor as i32 {
-                    map[y as usize][x as usize] = TileType::Floor as i32;
-                }
-            }
-        }
+        carve_maze(
+            &mut map,
+            params.algorithm,
+            corridor_width,
+            params.align_to_grid,
+        );
 
         // make maze passages sparser by trimming some dead ends
-        for _ in 0..5 {
-            trim_dead_ends(&mut map);
+        remove_dead_ends(&mut map, DeadEndRemoval::Passes(params.dead_end_passes));
+
+        // connect rooms & passages into one region
+        connect_regions(&mut map, params.extra_connector_chance);
+
+        let mut connections: Vec<((i32, i32), (i32, i32))> = Vec::new();
+        if let Some(room_connections) = &params.room_connections {
+            let centers: Vec<(i32, i32)> = rooms.iter().map(|r| r.center()).collect();
+            for (a, b) in plan_connections(&centers, room_connections) {
+                let corridor = CorridorParams::default();
+                carve_corridor(&mut map, centers[a], centers[b], &corridor);
+                connections.push((centers[a], centers[b]));
+            }
         }
 
-        // connect rooms with passages
-        connect_rooms(&mut rooms, &mut map);
-
-        map
+        let debug = DebugInfo {
+            rooms: rooms.iter().map(|r| r.rect()).collect(),
+            connections,
+            ..Default::default()
+        };
+        (map, debug)
     }
 }
@@ -4,3 +4,114 @@ pub mod maze_with_rooms;
 pub mod room_placement;
 pub mod rwalk;
 pub mod tunneling;
+
+use crate::maptools::{Map, Rect};
+use bsp_tree::{BSPTreeGenerator, BSPTreeParams};
+use cellular_automata::{CellularAutomataGenerator, CellularAutomataParams};
+use macroquad::rand;
+use maze_with_rooms::{MazeGenerator, MazeParams};
+use room_placement::{ConnectionPointLocation, RoomPlacementGenerator, RoomPlacementParams};
+use rwalk::{RandomWalkGenerator, RandomWalkParams};
+use serde::Serialize;
+use tunneling::{TunnelingGenerator, TunnelingParams};
+
+/// DebugConnectionPoint is a room placement connection point, in map coordinates
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DebugConnectionPoint {
+    pub x: i32,
+    pub y: i32,
+    pub loc: ConnectionPointLocation,
+    /// true if a room was attached to it
+    pub used: bool,
+}
+
+/// DebugInfo holds the intermediate structures a generator built the map from, for overlays.
+/// Generators fill in only what they have, the rest stays empty.
+#[derive(Clone, Debug, Default)]
+pub struct DebugInfo {
+    /// BSP node areas along with their depth in the tree, root first
+    pub partitions: Vec<(Rect, i32)>,
+    pub rooms: Vec<Rect>,
+    pub connection_points: Vec<DebugConnectionPoint>,
+    /// planned corridors, between room centers
+    pub connections: Vec<((i32, i32), (i32, i32))>,
+}
+
+/// Generator is any of the generators along with its parameters
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum Generator {
+    Tunneling(TunnelingParams),
+    BSPTree(BSPTreeParams),
+    RandomWalk(RandomWalkParams),
+    CellularAutomata(CellularAutomataParams),
+    RoomPlacement(RoomPlacementParams),
+    Maze(MazeParams),
+}
+
+impl Generator {
+    /// all returns every generator with default parameters
+    pub fn all() -> [Generator; 6] {
+        [
+            Generator::Tunneling(TunnelingParams::default()),
+            Generator::BSPTree(BSPTreeParams::default()),
+            Generator::RandomWalk(RandomWalkParams::default()),
+            Generator::CellularAutomata(CellularAutomataParams::default()),
+            Generator::RoomPlacement(RoomPlacementParams::default()),
+            Generator::Maze(MazeParams::default()),
+        ]
+    }
+
+    /// index returns the position of the generator in all()
+    pub fn index(&self) -> usize {
+        match self {
+            Generator::Tunneling(_) => 0,
+            Generator::BSPTree(_) => 1,
+            Generator::RandomWalk(_) => 2,
+            Generator::CellularAutomata(_) => 3,
+            Generator::RoomPlacement(_) => 4,
+            Generator::Maze(_) => 5,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Generator::Tunneling(_) => "tunneling",
+            Generator::BSPTree(_) => "BSP",
+            Generator::RandomWalk(_) => "random walk",
+            Generator::CellularAutomata(_) => "cell. automata",
+            Generator::RoomPlacement(_) => "room placement",
+            Generator::Maze(_) => "maze with rooms",
+        }
+    }
+
+    pub fn generate(&self) -> Map {
+        self.generate_debug().0
+    }
+
+    /// generate_debug returns the map along with the generator's debug info
+    pub fn generate_debug(&self) -> (Map, DebugInfo) {
+        match self {
+            Generator::Tunneling(p) => TunnelingGenerator::generate_map_debug(p),
+            Generator::BSPTree(p) => BSPTreeGenerator::generate_map_debug(p),
+            Generator::RandomWalk(p) => {
+                (RandomWalkGenerator::generate_map(p), DebugInfo::default())
+            }
+            Generator::CellularAutomata(p) => (
+                CellularAutomataGenerator::generate_map(p),
+                DebugInfo::default(),
+            ),
+            Generator::RoomPlacement(p) => RoomPlacementGenerator::generate_map_debug(p),
+            Generator::Maze(p) => MazeGenerator::generate_map_debug(p),
+        }
+    }
+
+    /// generate_seeded reseeds the PRNG first, so the same seed & parameters give the same map
+    pub fn generate_seeded(&self, seed: u64) -> Map {
+        self.generate_seeded_debug(seed).0
+    }
+
+    pub fn generate_seeded_debug(&self, seed: u64) -> (Map, DebugInfo) {
+        rand::srand(seed);
+        self.generate_debug()
+    }
+}
@@ -1,4 +1,6 @@
+use super::{DebugConnectionPoint, DebugInfo};
 use crate::{fundamentals::*, maptools::*, utils::*};
+use serde::Serialize;
 
 const SQUARE_ROOM_MIN_SIZE: i32 = 4;
 const SQUARE_ROOM_MAX_SIZE: i32 = 8;
@@ -6,6 +8,14 @@ const CROSS_ROOM_MIN_SIZE: i32 = 6;
 const CROSS_ROOM_MAX_SIZE: i32 = 16;
 const CAVE_ROOM_MIN_SIZE: i32 = 8;
 const CAVE_ROOM_MAX_SIZE: i32 = 14;
+const SHAPED_ROOM_MIN_SIZE: i32 = 7;
+const SHAPED_ROOM_MAX_SIZE: i32 = 13;
+/// caves with less floor than this are generated again, unless the room is too small for it
+const CAVE_ROOM_MIN_FLOOR: i32 = 80;
+/// caves generated before giving up on a cave room & falling back to a square room
+const CAVE_ROOM_TRIES: i32 = 20;
+/// free space is measured this many tiles in front of a connection point, and as wide
+const FREE_SPACE_DEPTH: i32 = 10;
 
 #[derive(Debug)]
 struct Room {
@@ -43,8 +53,9 @@ struct ConnectionPoint {
     loc: ConnectionPointLocation,
 }
 
+/// ConnectionPointLocation is the side of a room a connection point lies on
 #[derive(Copy, Clone, Debug, PartialEq)]
-enum ConnectionPointLocation {
+pub enum ConnectionPointLocation {
     Top,
     Right,
     Bottom,
@@ -197,10 +208,13 @@ fn generate_circular_room(size_min: i32, size_max: i32) -> Room {
 fn generate_cave_room(size_min: i32, size_max: i32) -> Room {
     let mut r = generate_square_room(size_min, size_max);
 
-    generate_cave(&mut r);
-    while get_random_cave_size(&mut r) < 80 {
-        r = generate_square_room(size_min, size_max);
+    let min_floor = CAVE_ROOM_MIN_FLOOR.min(size_max * size_max / 2);
+    for _ in 0..CAVE_ROOM_TRIES {
         generate_cave(&mut r);
+        if get_random_cave_size(&mut r) >= min_floor {
+            return r;
+        }
+        r = generate_square_room(size_min, size_max);
     }
     r
 }
@@ -269,12 +283,11 @@ fn get_random_cave_size(r: &mut Room) -> i32 {
     }
 
     // get random floor starting position for DFS
-    let mut rx = randr(0..r.rect.w);
-    let mut ry = randr(0..r.rect.h);
-    while r.tiles[room_get_xy(rx, ry, r.rect.w) as usize] as i32 != TileType::Floor as i32 {
-        rx = randr(0..r.rect.w);
-        ry = randr(0..r.rect.h);
-    }
+    let floors: Vec<i32> = (0..r.tiles.len() as i32)
+        .filter(|&i| r.tiles[i as usize] as i32 == TileType::Floor as i32)
+        .collect();
+    let start = pick(&floors);
+    let (rx, ry) = (start % r.rect.w, start / r.rect.w);
     let mut visited: Vec<i32> = Vec::with_capacity((r.rect.w * r.rect.h) as usize);
     for _ in r.tiles.iter() {
         visited.push(0);
@@ -323,6 +336,142 @@ fn is_valid(x: i32, y: i32, v: &[i32], r: &Room) -> bool {
     true
 }
 
+fn set_tile(r: &mut Room, x: i32, y: i32, tile: TileType) {
+    let idx = room_get_xy(x, y, r.rect.w) as usize;
+    r.tiles[idx] = tile;
+}
+
+fn generate_l_shaped_room(size_min: i32, size_max: i32) -> Room {
+    let mut r = generate_rectangular_room(size_min, size_max);
+    // cut out one corner, leaving both arms at least half as wide as the room
+    let (cut_w, cut_h) = (r.rect.w / 2, r.rect.h / 2);
+    let (x0, y0) = match randr(0..4) {
+        0 => (0, 0),
+        1 => (r.rect.w - cut_w, 0),
+        2 => (0, r.rect.h - cut_h),
+        _ => (r.rect.w - cut_w, r.rect.h - cut_h),
+    };
+    for x in x0..x0 + cut_w {
+        for y in y0..y0 + cut_h {
+            set_tile(&mut r, x, y, TileType::Wall);
+        }
+    }
+    r
+}
+
+fn generate_ring_room(size_min: i32, size_max: i32) -> Room {
+    let mut r = generate_circular_room(size_min, size_max);
+    let pillar_radius = (r.rect.w / 6).max(1);
+    let (cx, cy) = r.center();
+    for x in 0..r.rect.w {
+        for y in 0..r.rect.h {
+            let dist = (((x - cx).pow(2) + (y - cy).pow(2)) as f64).sqrt();
+            if dist.round() < pillar_radius as f64 {
+                set_tile(&mut r, x, y, TileType::Wall);
+            }
+        }
+    }
+    r
+}
+
+fn generate_pillared_room(size_min: i32, size_max: i32) -> Room {
+    let mut r = generate_rectangular_room(size_min, size_max);
+    // pillars on every third tile, keeping a free aisle along the walls
+    for x in (2..r.rect.w - 2).step_by(3) {
+        for y in (2..r.rect.h - 2).step_by(3) {
+            set_tile(&mut r, x, y, TileType::Wall);
+        }
+    }
+    r
+}
+
+fn generate_octagonal_room(size_min: i32, size_max: i32) -> Room {
+    let mut r = generate_square_room(size_min, size_max);
+    let cut = r.rect.w / 3;
+    let last = r.rect.w - 1;
+    for x in 0..r.rect.w {
+        for y in 0..r.rect.h {
+            let corner_dist = [x + y, last - x + y, x + last - y, 2 * last - x - y];
+            if corner_dist.iter().any(|&d| d < cut) {
+                set_tile(&mut r, x, y, TileType::Wall);
+            }
+        }
+    }
+    r
+}
+
+/// generate_polygon_room fills a random polygon whose corners are spread around the center,
+/// at varying distances from it
+fn generate_polygon_room(size_min: i32, size_max: i32) -> Room {
+    let mut r = generate_square_room(size_min, size_max);
+    let half = r.rect.w as f64 / 2.0;
+    let corners = randr(5..9);
+    let polygon: Vec<(f64, f64)> = (0..corners)
+        .map(|i| {
+            let jitter = randr(-30..31) as f64 / 100.0;
+            let angle = (i as f64 + jitter) / corners as f64 * std::f64::consts::TAU;
+            let dist = half * randr(55..101) as f64 / 100.0;
+            (half + dist * angle.cos(), half + dist * angle.sin())
+        })
+        .collect();
+    for x in 0..r.rect.w {
+        for y in 0..r.rect.h {
+            let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+            // even-odd rule, count the polygon edges crossed going right from the tile center
+            let mut inside = false;
+            for i in 0..polygon.len() {
+                let (ax, ay) = polygon[i];
+                let (bx, by) = polygon[(i + 1) % polygon.len()];
+                if (ay > py) != (by > py) && px < ax + (py - ay) / (by - ay) * (bx - ax) {
+                    inside = !inside;
+                }
+            }
+            if !inside {
+                set_tile(&mut r, x, y, TileType::Wall);
+            }
+        }
+    }
+    // thin spikes can leave tiles cut off from the rest, keep the part around the center
+    let (cx, cy) = r.center();
+    let mut visited = vec![0; r.tiles.len()];
+    dfs(cx, cy, &mut visited, &r);
+    for (pos, tile) in r.tiles.iter_mut().enumerate() {
+        if visited[pos] == 0 {
+            *tile = TileType::Wall;
+        }
+    }
+    r
+}
+
+/// generate_alcove_room is a rectangle with niches dug into its walls
+fn generate_alcove_room(size_min: i32, size_max: i32) -> Room {
+    let mut r = generate_rectangular_room(size_min, size_max);
+    let (w, h) = (r.rect.w, r.rect.h);
+    for x in 0..w {
+        for y in 0..h {
+            if x == 0 || y == 0 || x == w - 1 || y == h - 1 {
+                set_tile(&mut r, x, y, TileType::Wall);
+            }
+        }
+    }
+    // every other tile along each side may hold a niche, but never the corners
+    for x in (2..w - 2).step_by(2) {
+        for y in [0, h - 1] {
+            if randr(0..2) == 0 {
+                set_tile(&mut r, x, y, TileType::Floor);
+            }
+        }
+    }
+    for y in (2..h - 2).step_by(2) {
+        for x in [0, w - 1] {
+            if randr(0..2) == 0 {
+                set_tile(&mut r, x, y, TileType::Floor);
+            }
+        }
+    }
+    r
+}
+
 /// place_room transposes the Room rect to map coordinates, and carves the Room tiles
 fn place_room(r: &mut Room, m: &mut Map, xoff: i32, yoff: i32) -> bool {
     r.rect.x = xoff;
@@ -333,7 +482,7 @@ fn place_room(r: &mut Room, m: &mut Map, xoff: i32, yoff: i32) -> bool {
             if !in_bounds(x, y) {
                 return false;
             }
-            if m[y as usize][x as usize] == TileType::Floor as i32 {
+            if is_floor(m, x, y) {
                 return false;
             }
         }
@@ -348,44 +497,268 @@ fn place_room(r: &mut Room, m: &mut Map, xoff: i32, yoff: i32) -> bool {
     true
 }
 
-const N_ROOM_TYPE: i32 = 5;
+const N_ROOM_TYPE: usize = 11;
+#[derive(Copy, Clone, Debug)]
 enum RoomType {
     Square,
     Rectangle,
     Cross,
     Circle,
     Cave,
+    LShaped,
+    Ring,
+    Pillared,
+    Octagon,
+    Polygon,
+    Alcoves,
 }
 
-fn generate_random_room() -> Room {
-    let room_type: RoomType = match randr(0..N_ROOM_TYPE) {
-        0 => RoomType::Square,
-        1 => RoomType::Rectangle,
-        2 => RoomType::Cross,
-        3 => RoomType::Circle,
-        _ => RoomType::Cave,
-    };
-    let r = match room_type {
-        RoomType::Square => generate_square_room(SQUARE_ROOM_MIN_SIZE, SQUARE_ROOM_MAX_SIZE),
-        RoomType::Rectangle => {
-            generate_rectangular_room(SQUARE_ROOM_MIN_SIZE, SQUARE_ROOM_MAX_SIZE)
-        }
-        RoomType::Cross => generate_cross_room(CROSS_ROOM_MIN_SIZE, CROSS_ROOM_MAX_SIZE),
-        RoomType::Circle => generate_circular_room(SQUARE_ROOM_MIN_SIZE, SQUARE_ROOM_MAX_SIZE),
-        RoomType::Cave => generate_cave_room(CAVE_ROOM_MIN_SIZE, CAVE_ROOM_MAX_SIZE),
+/// RoomShape is how often a room shape is picked, relative to the others, and its size range
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct RoomShape {
+    pub weight: i32,
+    pub size_min: i32,
+    pub size_max: i32,
+}
+
+impl RoomShape {
+    fn new(size_min: i32, size_max: i32) -> Self {
+        Self {
+            weight: 1,
+            size_min,
+            size_max,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct RoomShapes {
+    pub square: RoomShape,
+    pub rectangle: RoomShape,
+    pub cross: RoomShape,
+    pub circle: RoomShape,
+    pub cave: RoomShape,
+    pub l_shaped: RoomShape,
+    /// a circle around a pillar
+    pub ring: RoomShape,
+    /// a rectangle with a grid of pillars
+    pub pillared: RoomShape,
+    pub octagon: RoomShape,
+    /// irregular polygon
+    pub polygon: RoomShape,
+    /// a rectangle with niches along its walls
+    pub alcoves: RoomShape,
+}
+
+impl Default for RoomShapes {
+    fn default() -> Self {
+        Self {
+            square: RoomShape::new(SQUARE_ROOM_MIN_SIZE, SQUARE_ROOM_MAX_SIZE),
+            rectangle: RoomShape::new(SQUARE_ROOM_MIN_SIZE, SQUARE_ROOM_MAX_SIZE),
+            cross: RoomShape::new(CROSS_ROOM_MIN_SIZE, CROSS_ROOM_MAX_SIZE),
+            circle: RoomShape::new(SQUARE_ROOM_MIN_SIZE, SQUARE_ROOM_MAX_SIZE),
+            cave: RoomShape::new(CAVE_ROOM_MIN_SIZE, CAVE_ROOM_MAX_SIZE),
+            l_shaped: RoomShape::new(SHAPED_ROOM_MIN_SIZE, SHAPED_ROOM_MAX_SIZE),
+            ring: RoomShape::new(SHAPED_ROOM_MIN_SIZE, SHAPED_ROOM_MAX_SIZE),
+            pillared: RoomShape::new(SHAPED_ROOM_MIN_SIZE, SHAPED_ROOM_MAX_SIZE),
+            octagon: RoomShape::new(SHAPED_ROOM_MIN_SIZE, SHAPED_ROOM_MAX_SIZE),
+            polygon: RoomShape::new(SHAPED_ROOM_MIN_SIZE, SHAPED_ROOM_MAX_SIZE),
+            alcoves: RoomShape::new(SHAPED_ROOM_MIN_SIZE, SHAPED_ROOM_MAX_SIZE),
+        }
+    }
+}
+
+impl RoomShapes {
+    fn all(&self) -> [(RoomType, RoomShape); N_ROOM_TYPE] {
+        [
+            (RoomType::Square, self.square),
+            (RoomType::Rectangle, self.rectangle),
+            (RoomType::Cross, self.cross),
+            (RoomType::Circle, self.circle),
+            (RoomType::Cave, self.cave),
+            (RoomType::LShaped, self.l_shaped),
+            (RoomType::Ring, self.ring),
+            (RoomType::Pillared, self.pillared),
+            (RoomType::Octagon, self.octagon),
+            (RoomType::Polygon, self.polygon),
+            (RoomType::Alcoves, self.alcoves),
+        ]
+    }
+}
+
+/// smallest size each room type still has its shape at
+fn room_type_min_size(room_type: RoomType) -> i32 {
+    match room_type {
+        RoomType::Square | RoomType::Rectangle | RoomType::Circle => 2,
+        RoomType::Cross | RoomType::LShaped => 3,
+        RoomType::Octagon | RoomType::Alcoves => 5,
+        RoomType::Ring | RoomType::Pillared | RoomType::Polygon | RoomType::Cave => 6,
+    }
+}
+
+fn generate_random_room(shapes: &RoomShapes) -> Room {
+    let all = shapes.all();
+    let total: i32 = all.iter().map(|(_, s)| s.weight.max(0)).sum();
+    // with no weights set every shape is equally likely
+    let weight = |s: &RoomShape| if total > 0 { s.weight.max(0) } else { 1 };
+    let sum: i32 = all.iter().map(|(_, s)| weight(s)).sum();
+    let mut roll = randr(0..sum);
+    let (room_type, shape) = all
+        .iter()
+        .find(|(_, s)| {
+            roll -= weight(s);
+            roll < 0
+        })
+        .copied()
+        .unwrap_or(all[0]);
+
+    let size_min = shape.size_min.max(room_type_min_size(room_type));
+    let size_max = shape.size_max.max(size_min);
+    match room_type {
+        RoomType::Square => generate_square_room(size_min, size_max),
+        RoomType::Rectangle => generate_rectangular_room(size_min, size_max),
+        RoomType::Cross => generate_cross_room(size_min, size_max),
+        RoomType::Circle => generate_circular_room(size_min, size_max),
+        RoomType::Cave => generate_cave_room(size_min, size_max),
+        RoomType::LShaped => generate_l_shaped_room(size_min, size_max),
+        RoomType::Ring => generate_ring_room(size_min, size_max),
+        RoomType::Pillared => generate_pillared_room(size_min, size_max),
+        RoomType::Octagon => generate_octagonal_room(size_min, size_max),
+        RoomType::Polygon => generate_polygon_room(size_min, size_max),
+        RoomType::Alcoves => generate_alcove_room(size_min, size_max),
+    }
+}
+
+/// direction a corridor leaves a room in from a connection point on the given side
+fn outward(loc: ConnectionPointLocation) -> (i32, i32) {
+    match loc {
+        ConnectionPointLocation::Top => (0, -1),
+        ConnectionPointLocation::Right => (1, 0),
+        ConnectionPointLocation::Bottom => (0, 1),
+        ConnectionPointLocation::Left => (-1, 0),
+    }
+}
+
+/// straight_corridor heads out of the connection point until it runs into the room's floor,
+/// crossing any other floor on the way
+fn straight_corridor(map: &Map, cp: &ConnectionPoint, room: &Rect) -> Vec<(i32, i32)> {
+    let (dx, dy) = outward(cp.loc);
+    let (mut x, mut y) = (cp.tile.x + dx, cp.tile.y + dy);
+    let in_room = |x: i32, y: i32| {
+        (room.x..room.x + room.w).contains(&x) && (room.y..room.y + room.h).contains(&y)
     };
-    r
+    let mut path: Vec<(i32, i32)> = Vec::new();
+    while in_bounds(x, y) && !(in_room(x, y) && is_floor(map, x, y)) {
+        path.push((x, y));
+        x += dx;
+        y += dy;
+    }
+    path
 }
 
-fn try_place_room(free_connection_points: &mut Vec<ConnectionPoint>, map: &mut Map) -> bool {
-    // generate a random type room
-    let mut r = generate_random_room();
+/// bent_corridor heads out of the connection point, turns sideways after turn_at tiles
+/// to line up with the entry, then heads on to it
+fn bent_corridor(cp: &ConnectionPoint, entry: &ConnectionPoint, turn_at: i32) -> Vec<(i32, i32)> {
+    let (dx, dy) = outward(cp.loc);
+    let to = (entry.tile.x, entry.tile.y);
+    let (mut x, mut y) = (cp.tile.x, cp.tile.y);
+    let mut path: Vec<(i32, i32)> = Vec::new();
+    for _ in 0..turn_at {
+        x += dx;
+        y += dy;
+        path.push((x, y));
+    }
+    while (dx == 0 && x != to.0) || (dy == 0 && y != to.1) {
+        x += (to.0 - x).signum() * (dx == 0) as i32;
+        y += (to.1 - y).signum() * (dy == 0) as i32;
+        path.push((x, y));
+    }
+    while (x, y) != to && in_bounds(x + dx, y + dy) {
+        x += dx;
+        y += dy;
+        path.push((x, y));
+    }
+    path.retain(|&t| t != to);
+    path
+}
+
+/// free_space counts the tiles without floor in an area in front of the connection point
+fn free_space(map: &Map, cp: &ConnectionPoint) -> i32 {
+    let (dx, dy) = outward(cp.loc);
+    // the sideways axis
+    let (sx, sy) = (dy.abs(), dx.abs());
+    let mut free = 0;
+    for d in 1..=FREE_SPACE_DEPTH {
+        for side in -FREE_SPACE_DEPTH / 2..=FREE_SPACE_DEPTH / 2 {
+            let (x, y) = (
+                cp.tile.x + dx * d + sx * side,
+                cp.tile.y + dy * d + sy * side,
+            );
+            if in_bounds(x, y) && !is_floor(map, x, y) {
+                free += 1;
+            }
+        }
+    }
+    free
+}
 
-    // select random connection point on starting room
-    let cp = free_connection_points[randr(0..free_connection_points.len() as i32) as usize];
+/// pick_connection_point selects a random free connection point,
+/// optionally favoring the ones with more free space in front of them
+fn pick_connection_point(
+    free_connection_points: &[ConnectionPoint],
+    map: &Map,
+    params: &RoomPlacementParams,
+) -> ConnectionPoint {
+    let uniform = pick(free_connection_points);
+    if !params.prefer_free_space {
+        return uniform;
+    }
+    let weights: Vec<i32> = free_connection_points
+        .iter()
+        .map(|cp| free_space(map, cp))
+        .collect();
+    let total: i32 = weights.iter().sum();
+    if total == 0 {
+        return uniform;
+    }
+    let mut roll = randr(0..total);
+    for (cp, weight) in free_connection_points.iter().zip(weights) {
+        roll -= weight;
+        if roll < 0 {
+            return *cp;
+        }
+    }
+    uniform
+}
 
-    // try to move room next to starting room connection point, depending on cp location
-    let placement_offset = 4;
+fn try_place_room(
+    cp: ConnectionPoint,
+    free_connection_points: &mut Vec<ConnectionPoint>,
+    map: &mut Map,
+    params: &RoomPlacementParams,
+    debug: &mut DebugInfo,
+) -> bool {
+    // generate a random type room
+    let mut r = generate_random_room(&params.shapes);
+
+    // try to move room next to starting room connection point, depending on cp location,
+    // bent corridors lead to a room shifted sideways
+    let placement_offset = if params.corridor_length_max > params.corridor_length_min {
+        randr(params.corridor_length_min..params.corridor_length_max + 1)
+    } else {
+        params.corridor_length_min
+    }
+    .max(1);
+    let bent = placement_offset >= 3
+        && params.bent_corridor_chance > 0
+        && randr(0..100) < params.bent_corridor_chance;
+    let (shift_x, shift_y) = match (bent, cp.loc) {
+        (false, _) => (0, 0),
+        (true, ConnectionPointLocation::Top | ConnectionPointLocation::Bottom) => {
+            (randr(-r.rect.w / 2..r.rect.w / 2 + 1), 0)
+        }
+        (true, _) => (0, randr(-r.rect.h / 2..r.rect.h / 2 + 1)),
+    };
     let (tx, ty) = match cp.loc {
         ConnectionPointLocation::Top => (
             cp.tile.x - r.rect.w / 2,
@@ -399,103 +772,184 @@ fn try_place_room(free_connection_points: &mut Vec<ConnectionPoint>, map: &mut M
         ),
     };
 
-    if !place_room(&mut r, map, tx, ty) {
+    if !place_room(&mut r, map, tx + shift_x, ty + shift_y) {
         return false;
     }
 
     let mut new_room_connection_points = find_connection_points(&r);
 
-    match cp.loc {
-        ConnectionPointLocation::Top => {
-            new_room_connection_points.retain(|&p| p.loc != ConnectionPointLocation::Bottom)
-        }
-        ConnectionPointLocation::Right => {
-            new_room_connection_points.retain(|&p| p.loc != ConnectionPointLocation::Left)
-        }
-        ConnectionPointLocation::Bottom => {
-            new_room_connection_points.retain(|&p| p.loc != ConnectionPointLocation::Top)
-        }
-        ConnectionPointLocation::Left => {
-            new_room_connection_points.retain(|&p| p.loc != ConnectionPointLocation::Right)
-        }
-    }
+    // the new room's connection point facing the starting room is where the corridor ends
+    let facing = match cp.loc {
+        ConnectionPointLocation::Top => ConnectionPointLocation::Bottom,
+        ConnectionPointLocation::Right => ConnectionPointLocation::Left,
+        ConnectionPointLocation::Bottom => ConnectionPointLocation::Top,
+        ConnectionPointLocation::Left => ConnectionPointLocation::Right,
+    };
+    let entry = new_room_connection_points
+        .iter()
+        .find(|p| p.loc == facing)
+        .copied();
+    new_room_connection_points.retain(|&p| p.loc != facing);
 
     // connect rooms
-    match cp.loc {
-        ConnectionPointLocation::Top => {
-            let tx = cp.tile.x;
-            let mut ty = cp.tile.y;
-            while map[ty as usize - 1][tx as usize] != TileType::Floor as i32 {
-                map[ty as usize - 1][tx as usize] = TileType::Floor as i32;
-                ty = ty - 1;
-            }
-        }
-        ConnectionPointLocation::Bottom => {
-            let tx = cp.tile.x;
-            let mut ty = cp.tile.y;
-            while map[ty as usize + 1][tx as usize] != TileType::Floor as i32 {
-                map[ty as usize + 1][tx as usize] = TileType::Floor as i32;
-                ty = ty + 1;
-            }
-        }
-        ConnectionPointLocation::Left => {
-            let mut tx = cp.tile.x;
-            let ty = cp.tile.y;
-            while map[ty as usize][tx as usize - 1] != TileType::Floor as i32 {
-                map[ty as usize][tx as usize - 1] = TileType::Floor as i32;
-                tx = tx - 1;
-            }
-        }
-        ConnectionPointLocation::Right => {
-            let mut tx = cp.tile.x;
-            let ty = cp.tile.y;
-            while map[ty as usize][tx as usize + 1] != TileType::Floor as i32 {
-                map[ty as usize][tx as usize + 1] = TileType::Floor as i32;
-                tx = tx + 1;
+    let path = match entry.filter(|_| bent) {
+        Some(entry) => bent_corridor(&cp, &entry, (placement_offset + 1) / 2),
+        None => straight_corridor(map, &cp, &r.rect),
+    };
+    let corridor: Vec<(i32, i32)> = path
+        .into_iter()
+        .filter(|&(x, y)| !is_floor(map, x, y))
+        .collect();
+    for &(x, y) in &corridor {
+        map[y as usize][x as usize] = TileType::Floor as i32;
+    }
+
+    // doors go on the corridor ends, right outside each room
+    if params.door_chance > 0 {
+        let mut ends = vec![corridor.first(), corridor.last()];
+        ends.dedup();
+        for &(x, y) in ends.into_iter().flatten() {
+            if randr(0..100) < params.door_chance {
+                map[y as usize][x as usize] = TileType::Door as i32;
             }
         }
     }
 
     // remove used up connection point on previous room
     free_connection_points.retain(|&p| (p != cp));
+    debug.rooms.push(r.rect);
+    debug
+        .connection_points
+        .push(debug_connection_point(&cp, true));
     // add new room's free connection points
     free_connection_points.extend(new_room_connection_points);
 
     true
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct RoomPlacementParams {
+    /// connection points tried, each with up to room_tries rooms
+    pub max_attempts: i32,
+    pub room_tries: i32,
+    /// placing stops once this many rooms are on the map
+    pub max_rooms: i32,
+    /// maps with less rooms are generated again, up to max_restarts times
+    pub min_rooms: i32,
+    pub max_restarts: i32,
+    /// pick connection points with more free space in front of them more often
+    pub prefer_free_space: bool,
+    pub shapes: RoomShapes,
+    /// corridors between rooms are this many tiles long, the range being inclusive
+    pub corridor_length_min: i32,
+    pub corridor_length_max: i32,
+    /// chance (0-100) for a corridor to bend, leading to a room shifted sideways
+    pub bent_corridor_chance: i32,
+    /// chance (0-100) for a door tile at each end of a corridor
+    pub door_chance: i32,
+}
+
+impl Default for RoomPlacementParams {
+    fn default() -> Self {
+        Self {
+            max_attempts: 100,
+            room_tries: 3,
+            max_rooms: 100,
+            min_rooms: 8,
+            max_restarts: 10,
+            prefer_free_space: true,
+            shapes: RoomShapes::default(),
+            corridor_length_min: 4,
+            corridor_length_max: 4,
+            bent_corridor_chance: 0,
+            door_chance: 0,
+        }
+    }
+}
+
+fn debug_connection_point(cp: &ConnectionPoint, used: bool) -> DebugConnectionPoint {
+    DebugConnectionPoint {
+        x: cp.tile.x,
+        y: cp.tile.y,
+        loc: cp.loc,
+        used,
+    }
+}
+
 pub struct RoomPlacementGenerator {}
 
 impl RoomPlacementGenerator {
-    pub fn generate_map() -> Map {
+    pub fn generate_map(params: &RoomPlacementParams) -> Map {
+        Self::generate_map_debug(params).0
+    }
+
+    pub fn generate_map_debug(params: &RoomPlacementParams) -> (Map, DebugInfo) {
+        let (map, debug, _) = Self::generate_map_full(params);
+        (map, debug)
+    }
+
+    /// generate_map_with_count also returns the number of rooms placed
+    pub fn generate_map_with_count(params: &RoomPlacementParams) -> (Map, i32) {
+        let (map, _, rooms_placed) = Self::generate_map_full(params);
+        (map, rooms_placed)
+    }
+
+    /// generate_map_full starts over while less than min_rooms got placed,
+    /// keeping the map with the most rooms once out of restarts
+    fn generate_map_full(params: &RoomPlacementParams) -> (Map, DebugInfo, i32) {
+        let mut best = Self::place_rooms(params);
+        for _ in 0..params.max_restarts {
+            if best.2 >= params.min_rooms {
+                break;
+            }
+            let next = Self::place_rooms(params);
+            if next.2 > best.2 {
+                best = next;
+            }
+        }
+        best
+    }
+
+    fn place_rooms(params: &RoomPlacementParams) -> (Map, DebugInfo, i32) {
+        let mut debug = DebugInfo::default();
         let mut map = new_map(TileType::Wall);
 
         // generate & place starting room in center
-        let mut r1 = generate_random_room();
+        let mut r1 = generate_random_room(&params.shapes);
         let starting_room_x = COLS / 2 - r1.rect.w / 2;
         let starting_room_y = ROWS / 2 - r1.rect.h / 2;
         place_room(&mut r1, &mut map, starting_room_x, starting_room_y);
+        debug.rooms.push(r1.rect);
 
         // add starting room's connection points to vec containg all free connection points
         let mut free_connection_points = find_connection_points(&r1);
 
-        let max_attempts = 100;
+        // every attempt tries a few rooms at one connection point, so this always terminates
         let mut rooms_placed = 1;
-        for _ in 0..max_attempts {
-            if try_place_room(&mut free_connection_points, &mut map) {
-                rooms_placed = rooms_placed + 1;
+        for _ in 0..params.max_attempts {
+            if rooms_placed >= params.max_rooms || free_connection_points.is_empty() {
+                break;
+            }
+            let cp = pick_connection_point(&free_connection_points, &map, params);
+            for _ in 0..params.room_tries.max(1) {
+                if try_place_room(
+                    cp,
+                    &mut free_connection_points,
+                    &mut map,
+                    params,
+                    &mut debug,
+                ) {
+                    rooms_placed += 1;
+                    break;
+                }
             }
         }
 
-        // println!(
-        //     "Placed {} rooms out of {} attempts.",
-        //     rooms_placed, max_attempts
-        // );
-
-        // for c in free_connection_points {
-        //     map[get_xy_idx(c.tile.x, c.tile.y) as usize] = TileType::Hero as i32;
-        // }
-
-        map
+        debug.connection_points.extend(
+            free_connection_points
+                .iter()
+                .map(|cp| debug_connection_point(cp, false)),
+        );
+        (map, debug, rooms_placed)
     }
 }
@@ -1,89 +1,258 @@
 use crate::{fundamentals::*, maptools::*, utils::*};
+use serde::Serialize;
 
-const MAX_WALKERS: i32 = 10;
-const MAX_STEPS: i32 = 200;
+/// the first 4 are the cardinal directions, 8-direction walks also use the diagonals
+const DIRECTIONS: [(i32, i32); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum WalkBias {
+    None,
+    /// prefer stepping left or right, gives long horizontal corridors
+    Horizontal,
+    /// prefer stepping up or down
+    Vertical,
+    /// prefer stepping towards the map center, gives compact caverns
+    Center,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum WalkOrigin {
+    /// all walkers start at the map center
+    Center,
+    /// every walker starts at its own random position
+    Random,
+}
 
 #[derive(Copy, Clone, Debug)]
 struct Walker {
     x: i32,
     y: i32,
     pub steps: i32,
+    /// direction of the last step
+    dir: (i32, i32),
 }
 
 impl Walker {
-    fn new(x: i32, y: i32) -> Self {
+    fn new(x: i32, y: i32, steps: i32) -> Self {
         Self {
             x,
             y,
-            steps: MAX_STEPS,
+            steps,
+            dir: (0, 0),
         }
     }
 
-    fn step(&mut self, map: &mut Map) {
-        if self.steps <= 0 {
-            return;
+    fn direction(&self, params: &RandomWalkParams) -> (i32, i32) {
+        if self.dir != (0, 0) && params.momentum > 0 && randr(0..100) < params.momentum {
+            return self.dir;
         }
+        if params.bias != WalkBias::None && randr(0..100) < params.bias_strength {
+            let (cx, cy) = ((COLS / 2 - self.x).signum(), (ROWS / 2 - self.y).signum());
+            let side = if randr(0..2) == 0 { 1 } else { -1 };
+            match params.bias {
+                WalkBias::Horizontal => return (side, 0),
+                WalkBias::Vertical => return (0, side),
+                WalkBias::Center if (cx, cy) != (0, 0) => {
+                    // one axis at a time, unless diagonal steps are allowed
+                    return match (params.diagonal, cx, cy) {
+                        (true, _, _) => (cx, cy),
+                        (false, 0, _) => (0, cy),
+                        (false, _, 0) => (cx, 0),
+                        _ if side > 0 => (cx, 0),
+                        _ => (0, cy),
+                    };
+                }
+                _ => {}
+            }
+        }
+        let n = if params.diagonal { 8 } else { 4 };
+        pick(&DIRECTIONS[..n])
+    }
 
-        let direction = randr(0..4);
-        let (mut dx, mut dy) = (0, 0);
-        match direction {
-            0 => dx = 1,
-            1 => dx = -1,
-            2 => dy = 1,
-            _ => dy = -1,
+    /// step moves the walker & carves its brush, returns the number of newly carved tiles
+    fn step(&mut self, map: &mut Map, params: &RandomWalkParams) -> i32 {
+        if self.steps <= 0 {
+            return 0;
         }
 
+        let (dx, dy) = self.direction(params);
         let tx = self.x + dx;
         let ty = self.y + dy;
 
         if tx < 1 || tx >= COLS - 1 || ty < 1 || ty >= ROWS - 1 {
-            return;
+            // turn away from the border, so momentum doesn't keep the walker stuck there
+            self.dir = (0, 0);
+            return 0;
         }
 
+        let mut carved = 0;
+        if dx != 0 && dy != 0 {
+            // diagonal step, carve a jog so the floor stays connected orthogonally
+            carved += carve(map, tx, self.y, params.brush_size);
+        }
         self.x = tx;
         self.y = ty;
+        self.dir = (dx, dy);
         self.steps -= 1;
-        map[self.y as usize][self.x as usize] = TileType::Floor as i32;
+        carved + carve(map, self.x, self.y, params.brush_size)
+    }
+}
+
+/// carve digs a square brush centered on (x, y), returns the number of newly carved tiles
+fn carve(map: &mut Map, x: i32, y: i32, brush_size: i32) -> i32 {
+    let size = brush_size.max(1);
+    let offset = (size - 1) / 2;
+    let mut carved = 0;
+    for by in y - offset..y - offset + size {
+        for bx in x - offset..x - offset + size {
+            // the brush never touches the border, so maps stay enclosed by walls
+            if !(1..COLS - 1).contains(&bx) || !(1..ROWS - 1).contains(&by) {
+                continue;
+            }
+            if map[by as usize][bx as usize] != TileType::Floor as i32 {
+                map[by as usize][bx as usize] = TileType::Floor as i32;
+                carved += 1;
+            }
+        }
     }
+    carved
 }
 
-fn spawn_walker<'a>(x: i32, y: i32, vec: &'a mut Vec<Walker>, map: &mut Map) {
-    vec.push(Walker::new(x, y));
-    map[y as usize][x as usize] = TileType::Floor as i32;
+fn random_floor(map: &Map) -> Option<(i32, i32)> {
+    let floor: Vec<(i32, i32)> = (0..ROWS)
+        .flat_map(|y| (0..COLS).map(move |x| (x, y)))
+        .filter(|&(x, y)| is_floor(map, x, y))
+        .collect();
+    if floor.is_empty() {
+        return None;
+    }
+    Some(pick(&floor))
+}
+
+fn spawn_walker(
+    x: i32,
+    y: i32,
+    vec: &mut Vec<Walker>,
+    map: &mut Map,
+    params: &RandomWalkParams,
+) -> i32 {
+    vec.push(Walker::new(x, y, params.max_steps));
+    carve(map, x, y, params.brush_size)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct RandomWalkParams {
+    pub max_walkers: i32,
+    pub max_steps: i32,
+    /// chance (0-100) to spawn a new walker after each step
+    pub spawn_chance: i32,
+    /// chance (0-100) to keep walking in the same direction
+    pub momentum: i32,
+    pub bias: WalkBias,
+    /// chance (0-100) for a step to follow the bias
+    pub bias_strength: i32,
+    /// walk in 8 directions instead of 4
+    pub diagonal: bool,
+    /// width & height of the square carved at each step
+    pub brush_size: i32,
+    /// number of walkers starting out
+    pub origins: i32,
+    pub origin: WalkOrigin,
+    /// spawned walkers start at a random floor tile instead of where their parent is
+    pub spawn_from_floor: bool,
+    /// when above 0, walking continues until this percentage (0-100) of the map is floor,
+    /// with new walkers starting at a random floor tile once all have run out of steps
+    pub target_floor: i32,
+}
+
+impl Default for RandomWalkParams {
+    fn default() -> Self {
+        Self {
+            max_walkers: 10,
+            max_steps: 200,
+            spawn_chance: 20,
+            momentum: 0,
+            bias: WalkBias::None,
+            bias_strength: 50,
+            diagonal: false,
+            brush_size: 1,
+            origins: 1,
+            origin: WalkOrigin::Center,
+            spawn_from_floor: false,
+            target_floor: 0,
+        }
+    }
 }
 
 pub struct RandomWalkGenerator {}
 impl RandomWalkGenerator {
-    pub fn generate_map() -> Map {
+    pub fn generate_map(params: &RandomWalkParams) -> Map {
         let mut map = new_map(TileType::Wall);
         let mut walkers: Vec<Walker> = Vec::new();
         let mut num_walkers = 0;
+        let mut num_floor = 0;
+        let target = params.target_floor.clamp(0, 100) * COLS * ROWS / 100;
+        // the brush can't reach the border, so the target may be out of reach
+        let mut restarts_left = COLS * ROWS;
 
-        spawn_walker(COLS / 2, ROWS / 2, &mut walkers, &mut map);
-        num_walkers += 1;
+        for _ in 0..params.origins.max(1) {
+            let (x, y) = match params.origin {
+                WalkOrigin::Center => (COLS / 2, ROWS / 2),
+                WalkOrigin::Random => (randr(1..COLS - 1), randr(1..ROWS - 1)),
+            };
+            num_floor += spawn_walker(x, y, &mut walkers, &mut map, params);
+            num_walkers += 1;
+        }
 
         // until we have active walkers
-        while walkers.len() > 0 {
+        while !walkers.is_empty() {
             // create vector for possible newly spawned walkers in this iteration
             let mut walkers_spawned: Vec<Walker> = Vec::new();
 
             // each walker takes step
             for w in &mut walkers {
-                w.step(&mut map);
-                // after each step, chance to spawn new walker at walker's current location (if we can)
-                if (num_walkers < MAX_WALKERS) & (randr(0..100) > 80) {
-                    spawn_walker(w.x, w.y, &mut walkers_spawned, &mut map);
+                num_floor += w.step(&mut map, params);
+                if target > 0 && num_floor >= target {
+                    w.steps = 0;
+                    continue;
+                }
+                // after each step, chance to spawn new walker at walker's current location
+                // or a random floor tile (if we can)
+                if (num_walkers < params.max_walkers) & (randr(0..100) < params.spawn_chance) {
+                    let (x, y) = if params.spawn_from_floor {
+                        random_floor(&map).unwrap_or((w.x, w.y))
+                    } else {
+                        (w.x, w.y)
+                    };
+                    num_floor += spawn_walker(x, y, &mut walkers_spawned, &mut map, params);
                     num_walkers += 1;
                 }
             }
 
             // if we did spawn new walker in this iteration, append it to the main walkers vector
-            if walkers_spawned.len() > 0 {
+            if !walkers_spawned.is_empty() {
                 walkers.append(&mut walkers_spawned);
             }
 
             // keep only walkers that still have steps left in main walkers vector
             walkers.retain(|x| x.steps > 0);
+
+            // keep walking from the existing floor until the target is reached
+            if walkers.is_empty() && num_floor < target && restarts_left > 0 {
+                restarts_left -= 1;
+                if let Some((x, y)) = random_floor(&map) {
+                    num_floor += spawn_walker(x, y, &mut walkers, &mut map, params);
+                }
+            }
         }
 
         map
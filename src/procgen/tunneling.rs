@@ -1,52 +1,147 @@
+use super::DebugInfo;
+use crate::connections::{plan_connections, ConnectionParams};
+use crate::corridor::{carve_corridor, CorridorParams};
 use crate::fundamentals::*;
 use crate::maptools::*;
 use crate::utils::*;
+use serde::Serialize;
+
+/// rects a compound room is made of at most, so overlapping rooms don't grow into one blob
+const MAX_COMPOUND_RECTS: usize = 3;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct TunnelingParams {
+    pub room_size_min: i32,
+    pub room_size_max: i32,
+    /// number of rooms tried, rooms that don't fit are dropped
+    pub room_attempts: i32,
+    /// stop placing rooms once this many are in, 0 for no target
+    pub target_rooms: i32,
+    /// least number of wall tiles between rooms
+    pub room_padding: i32,
+    /// let overlapping rooms in, merging them into compound rooms
+    pub allow_overlaps: bool,
+    pub connections: ConnectionParams,
+    pub corridor: CorridorParams,
+}
+
+impl Default for TunnelingParams {
+    fn default() -> Self {
+        Self {
+            room_size_min: 6,
+            room_size_max: 16,
+            room_attempts: 30,
+            target_rooms: 0,
+            room_padding: 1,
+            allow_overlaps: false,
+            connections: ConnectionParams::default(),
+            corridor: CorridorParams::default(),
+        }
+    }
+}
+
+/// PlacedRoom is a room of the generated map, a compound room is made of several overlapping rects
+#[derive(Clone, Debug)]
+pub struct PlacedRoom {
+    pub rects: Vec<Rect>,
+    /// where corridors attach, the center of the first rect
+    pub center: (i32, i32),
+}
+
+impl PlacedRoom {
+    pub fn is_compound(&self) -> bool {
+        self.rects.len() > 1
+    }
+}
+
+/// within tells if two rects come closer than padding wall tiles, with 0 if they share tiles
+fn within(a: &Rect, b: &Rect, padding: i32) -> bool {
+    a.x - padding < b.x + b.w
+        && b.x - padding < a.x + a.w
+        && a.y - padding < b.y + b.h
+        && b.y - padding < a.y + a.h
+}
 
 pub struct TunnelingGenerator {}
 impl TunnelingGenerator {
-    pub fn generate_map(room_size_min: i32, room_size_max: i32, max_rooms: i32) -> Map {
+    pub fn generate_map(params: &TunnelingParams) -> Map {
+        Self::generate_map_debug(params).0
+    }
+
+    pub fn generate_map_debug(params: &TunnelingParams) -> (Map, DebugInfo) {
+        let (map, debug, _) = Self::generate_map_full(params);
+        (map, debug)
+    }
+
+    /// generate_map_with_rooms also returns the rooms of the map
+    pub fn generate_map_with_rooms(params: &TunnelingParams) -> (Map, Vec<PlacedRoom>) {
+        let (map, _, rooms) = Self::generate_map_full(params);
+        (map, rooms)
+    }
+
+    fn generate_map_full(params: &TunnelingParams) -> (Map, DebugInfo, Vec<PlacedRoom>) {
         let mut map = new_map(TileType::Wall);
-        let mut rooms: Vec<Room> = vec![];
-        let mut num_rooms = 0;
+        let mut rooms: Vec<PlacedRoom> = vec![];
 
-        for _ in 0..max_rooms {
-            let w = randr(room_size_min..room_size_max);
-            let h = randr(room_size_min..room_size_max);
+        for _ in 0..params.room_attempts {
+            if params.target_rooms > 0 && rooms.len() as i32 >= params.target_rooms {
+                break;
+            }
+            let w = randr(params.room_size_min..params.room_size_max);
+            let h = randr(params.room_size_min..params.room_size_max);
             let x = randr(1..COLS - w);
             let y = randr(1..ROWS - h);
 
             let curr_room = Room::new(x, y, w, h);
-            let mut overlaps = false;
+            let rect = curr_room.rect();
 
-            for room in &rooms {
-                if curr_room.overlaps(*room) {
-                    overlaps = true;
-                    break;
+            // a room sharing tiles with the new one gets merged, the rest have to keep their distance
+            let overlapped: Vec<usize> = (0..rooms.len())
+                .filter(|&i| rooms[i].rects.iter().any(|r| within(&rect, r, 0)))
+                .collect();
+            let merged = match overlapped[..] {
+                [i] if params.allow_overlaps && rooms[i].rects.len() < MAX_COMPOUND_RECTS => {
+                    Some(i)
                 }
+                _ => None,
+            };
+            let crowded = rooms.iter().enumerate().any(|(i, room)| {
+                merged != Some(i)
+                    && room
+                        .rects
+                        .iter()
+                        .any(|r| within(&rect, r, params.room_padding))
+            });
+            if crowded {
+                continue;
             }
 
-            if !overlaps {
-                curr_room.carve(&mut map);
-
-                let (curr_x, curr_y) = curr_room.center();
-
-                if num_rooms != 0 {
-                    let prev_room = rooms[num_rooms - 1];
-                    let (prev_x, prev_y) = prev_room.center();
-
-                    if randr(0..1) == 1 {
-                        carve_horz_tunnel(&mut map, curr_x, prev_x, curr_y);
-                        carve_vert_tunnel(&mut map, curr_y, prev_y, prev_x);
-                    } else {
-                        carve_vert_tunnel(&mut map, curr_y, prev_y, curr_x);
-                        carve_horz_tunnel(&mut map, curr_x, prev_x, prev_y);
-                    }
-                }
-
-                num_rooms += 1;
-                rooms.push(curr_room);
+            curr_room.carve(&mut map);
+            match merged {
+                Some(i) => rooms[i].rects.push(rect),
+                None => rooms.push(PlacedRoom {
+                    rects: vec![rect],
+                    center: curr_room.center(),
+                }),
             }
         }
-        map
+
+        // corridors are carved once all rooms are in place, so routing can avoid them
+        let centers: Vec<(i32, i32)> = rooms.iter().map(|r| r.center).collect();
+        let connections: Vec<((i32, i32), (i32, i32))> =
+            plan_connections(&centers, &params.connections)
+                .into_iter()
+                .map(|(a, b)| (centers[a], centers[b]))
+                .collect();
+        for &(from, to) in &connections {
+            carve_corridor(&mut map, from, to, &params.corridor);
+        }
+
+        let debug = DebugInfo {
+            rooms: rooms.iter().flat_map(|r| r.rects.clone()).collect(),
+            connections,
+            ..Default::default()
+        };
+        (map, debug, rooms)
     }
 }
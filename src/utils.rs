@@ -6,6 +6,48 @@ pub fn randr(r: Range<i32>) -> i32 {
     rand::gen_range::<i32>(r.start, r.end)
 }
 
+/// pick_index returns a random index into a collection of len items, len must not be 0
+pub fn pick_index(len: usize) -> usize {
+    randr(0..len as i32) as usize
+}
+
+/// pick returns a random item, the items must not be empty
+pub fn pick<T: Copy>(items: &[T]) -> T {
+    items[pick_index(items.len())]
+}
+
 pub fn in_bounds(x: i32, y: i32) -> bool {
     0 <= x && x < COLS && 0 <= y && y < ROWS
 }
+
+/// line returns the tiles of a line between two points (Bresenham), both ends included
+pub fn line(x0: i32, y0: i32, x1: i32, y1: i32) -> Vec<(i32, i32)> {
+    let dx = (x1 - x0).abs();
+    let dy = -(y1 - y0).abs();
+    let sx = if x0 < x1 { 1 } else { -1 };
+    let sy = if y0 < y1 { 1 } else { -1 };
+    let mut err = dx + dy;
+    let (mut x, mut y) = (x0, y0);
+    let mut points = vec![(x, y)];
+    while (x, y) != (x1, y1) {
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+        points.push((x, y));
+    }
+    points
+}
+
+/// shuffle puts the items in random order (Fisher-Yates)
+pub fn shuffle<T>(items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        let j = pick_index(i + 1);
+        items.swap(i, j);
+    }
+}
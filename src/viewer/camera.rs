@@ -0,0 +1,114 @@
+use macroquad::prelude::*;
+use mapgen::fundamentals::{COLS, ROWS, TILESIZE};
+use std::ops::Range;
+
+const ZOOM_STEP: f32 = 1.1;
+const ZOOM_MIN: f32 = 0.1;
+const ZOOM_MAX: f32 = 8.0;
+
+/// MapCamera is a pannable & zoomable view of the map, in map pixel coordinates
+pub struct MapCamera {
+    /// map point shown in the center of the window
+    target: Vec2,
+    /// screen pixels per map pixel
+    scale: f32,
+    drag_start: Option<Vec2>,
+}
+
+impl MapCamera {
+    pub fn new() -> Self {
+        let mut camera = Self {
+            target: vec2(0.0, 0.0),
+            scale: 1.0,
+            drag_start: None,
+        };
+        camera.fit();
+        camera
+    }
+
+    /// fit zooms & centers the camera so the whole map fits the window
+    pub fn fit(&mut self) {
+        let map_w = (COLS * TILESIZE) as f32;
+        let map_h = (ROWS * TILESIZE) as f32;
+        self.scale = (screen_width() / map_w).min(screen_height() / map_h);
+        self.target = vec2(map_w / 2.0, map_h / 2.0);
+    }
+
+    pub fn camera(&self) -> Camera2D {
+        Camera2D {
+            target: self.target,
+            zoom: vec2(
+                2.0 * self.scale / screen_width(),
+                -2.0 * self.scale / screen_height(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    /// pane_camera draws into a part of the window at the same scale,
+    /// with the camera target in the center of the pane
+    pub fn pane_camera(&self, pane: Rect) -> Camera2D {
+        Camera2D {
+            target: self.target,
+            zoom: vec2(2.0 * self.scale / pane.w, -2.0 * self.scale / pane.h),
+            // viewports are in GL coordinates, which start at the bottom of the window
+            viewport: Some((
+                pane.x as i32,
+                (screen_height() - pane.y - pane.h) as i32,
+                pane.w as i32,
+                pane.h as i32,
+            )),
+            ..Default::default()
+        }
+    }
+
+    pub fn screen_to_world(&self, point: Vec2) -> Vec2 {
+        self.camera().screen_to_world(point)
+    }
+
+    /// update pans the camera while dragging with the given mouse buttons & zooms with the wheel
+    pub fn update(&mut self, pan_buttons: &[MouseButton]) {
+        let mouse = Vec2::from(mouse_position());
+
+        if pan_buttons.iter().any(|&b| is_mouse_button_down(b)) {
+            if let Some(start) = self.drag_start {
+                self.target -= (mouse - start) / self.scale;
+            }
+            self.drag_start = Some(mouse);
+        } else {
+            self.drag_start = None;
+        }
+
+        let (_, wheel) = mouse_wheel();
+        if wheel != 0.0 {
+            // keep the map point under the cursor in place while zooming
+            let before = self.screen_to_world(mouse);
+            let factor = if wheel > 0.0 {
+                ZOOM_STEP
+            } else {
+                1.0 / ZOOM_STEP
+            };
+            self.scale = (self.scale * factor).clamp(ZOOM_MIN, ZOOM_MAX);
+            self.target += before - self.screen_to_world(mouse);
+        }
+    }
+
+    /// visible_tiles returns the column & row ranges of tiles inside the window, so
+    /// everything off-screen can be skipped when drawing
+    pub fn visible_tiles(&self) -> (Range<i32>, Range<i32>) {
+        self.pane_tiles(Rect::new(0.0, 0.0, screen_width(), screen_height()))
+    }
+
+    /// pane_tiles returns the column & row ranges of tiles inside a pane drawn with pane_camera
+    pub fn pane_tiles(&self, pane: Rect) -> (Range<i32>, Range<i32>) {
+        let half = vec2(pane.w, pane.h) / 2.0 / self.scale;
+        let top_left = self.target - half;
+        let bottom_right = self.target + half;
+        let tile = TILESIZE as f32;
+        let cols = (top_left.x / tile).floor().max(0.0) as i32
+            ..((bottom_right.x / tile).ceil() as i32).min(COLS);
+        let rows = (top_left.y / tile).floor().max(0.0) as i32
+            ..((bottom_right.y / tile).ceil() as i32).min(ROWS);
+        (cols, rows)
+    }
+}
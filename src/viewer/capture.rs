@@ -0,0 +1,175 @@
+use super::history::timestamped;
+use macroquad::prelude::*;
+use std::fs::File;
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+
+/// frames captured per second while recording, also the GIF playback rate
+const GIF_FPS: f64 = 10.0;
+const GIF_MAX_FRAMES: usize = 300;
+/// recorded frames are scaled down by this factor, to keep memory use & file size reasonable
+const GIF_SCALE: usize = 2;
+
+struct Recording {
+    width: u16,
+    height: u16,
+    frames: Vec<Vec<u8>>,
+    last_frame_at: f64,
+}
+
+/// Capture saves rendered frames as PNG screenshots or records them into an animated GIF
+pub struct Capture {
+    /// render target the screen is copied into, recreated when the window is resized
+    target: Option<RenderTarget>,
+    recording: Option<Recording>,
+    /// result messages of GIFs being encoded in the background
+    encoding: Vec<Receiver<String>>,
+}
+
+impl Capture {
+    pub fn new() -> Self {
+        Self {
+            target: None,
+            recording: None,
+            encoding: Vec::new(),
+        }
+    }
+
+    /// grab copies the current screen contents, rows are stored bottom to top
+    fn grab(&mut self) -> Image {
+        let (w, h) = (screen_width() as u32, screen_height() as u32);
+        let resized = match &self.target {
+            Some(t) => t.texture.width() as u32 != w || t.texture.height() as u32 != h,
+            None => true,
+        };
+        if resized {
+            if let Some(t) = self.target.take() {
+                t.delete();
+            }
+            self.target = Some(render_target(w, h));
+        }
+
+        unsafe {
+            get_internal_gl().flush();
+        }
+        let texture = self.target.as_ref().unwrap().texture;
+        texture.grab_screen();
+        texture.get_texture_data()
+    }
+
+    /// screenshot saves the current frame to a timestamped PNG and returns its path
+    pub fn screenshot(&mut self) -> String {
+        let path = timestamped("png");
+        self.grab().export_png(&path);
+        path
+    }
+
+    /// toggle_recording starts recording, or stops it & saves the GIF, returning a status message
+    pub fn toggle_recording(&mut self) -> String {
+        match self.recording.take() {
+            Some(recording) => self.save(recording),
+            None => {
+                self.recording = Some(Recording {
+                    width: (screen_width() as usize / GIF_SCALE) as u16,
+                    height: (screen_height() as usize / GIF_SCALE) as u16,
+                    frames: Vec::new(),
+                    last_frame_at: f64::MIN,
+                });
+                "recording GIF".to_owned()
+            }
+        }
+    }
+
+    /// save encodes the recorded frames on a separate thread, so the viewer doesn't freeze
+    fn save(&mut self, recording: Recording) -> String {
+        if recording.frames.is_empty() {
+            return "recording stopped, no frames captured".to_owned();
+        }
+        let path = timestamped("gif");
+        let message = format!("saving {} ({} frames)", path, recording.frames.len());
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            let text = match encode_gif(&path, recording) {
+                Ok(_) => format!("saved {}", path),
+                Err(e) => format!("saving {} failed: {}", path, e),
+            };
+            sender.send(text).ok();
+        });
+        self.encoding.push(receiver);
+        message
+    }
+
+    /// update captures a frame while recording, call it once the frame is drawn.
+    /// Returns a status message when a recording was stopped or a GIF was saved.
+    pub fn update(&mut self) -> Option<String> {
+        let mut message = None;
+        self.encoding.retain(|receiver| match receiver.try_recv() {
+            Ok(text) => {
+                message = Some(text);
+                false
+            }
+            Err(e) => e == std::sync::mpsc::TryRecvError::Empty,
+        });
+
+        let due = match &self.recording {
+            Some(r) => get_time() - r.last_frame_at >= 1.0 / GIF_FPS,
+            None => false,
+        };
+        if due {
+            let image = self.grab();
+            let recording = self.recording.as_mut().unwrap();
+            // the window may have been resized while recording, frames keep the initial size
+            recording
+                .frames
+                .push(scale_frame(&image, recording.width, recording.height));
+            recording.last_frame_at = get_time();
+
+            if recording.frames.len() >= GIF_MAX_FRAMES {
+                let recording = self.recording.take().unwrap();
+                message = Some(self.save(recording));
+            }
+        }
+        message
+    }
+
+    /// draw_indicator marks the window while recording, draw it after update so it isn't recorded
+    pub fn draw_indicator(&self, params: TextParams) {
+        if let Some(recording) = &self.recording {
+            let x = screen_width() - 90.0;
+            draw_circle(x, screen_height() - 21.0, 6.0, RED);
+            let text = format!("REC {}", recording.frames.len());
+            draw_text_ex(&text, x + 12.0, screen_height() - 16.0, params);
+        }
+    }
+}
+
+/// scale_frame flips the screen image upright & scales it down to the GIF size,
+/// picking the nearest pixel so tile edges stay sharp
+fn scale_frame(image: &Image, width: u16, height: u16) -> Vec<u8> {
+    let (src_w, src_h) = (image.width as usize, image.height as usize);
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height as usize {
+        let src_y = src_h - 1 - (y * GIF_SCALE).min(src_h - 1);
+        for x in 0..width as usize {
+            let src_x = (x * GIF_SCALE).min(src_w - 1);
+            let idx = (src_y * src_w + src_x) * 4;
+            pixels.extend_from_slice(&image.bytes[idx..idx + 3]);
+            pixels.push(255);
+        }
+    }
+    pixels
+}
+
+fn encode_gif(path: &str, recording: Recording) -> Result<(), gif::EncodingError> {
+    let (width, height) = (recording.width, recording.height);
+    let mut file = File::create(path)?;
+    let mut encoder = gif::Encoder::new(&mut file, width, height, &[])?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+    for mut pixels in recording.frames {
+        let mut frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, 10);
+        // delay is in hundredths of a second
+        frame.delay = (100.0 / GIF_FPS) as u16;
+        encoder.write_frame(&frame)?;
+    }
+    Ok(())
+}
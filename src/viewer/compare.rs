@@ -0,0 +1,150 @@
+use super::camera::MapCamera;
+use super::theme::{render_map, Theme};
+use macroquad::prelude::*;
+use mapgen::maptools::Map;
+use mapgen::metrics::MapMetrics;
+use mapgen::procgen::Generator;
+
+/// pane grid size (columns, rows) comparisons start with
+pub const DEFAULT_GRID: (i32, i32) = (2, 2);
+/// most pane columns & rows, panes get too small to tell maps apart beyond this
+const GRID_MAX: i32 = 5;
+
+/// resize_grid adds (or with negative counts removes) pane columns & rows,
+/// keeping between 1 and GRID_MAX of each & at least two panes
+pub fn resize_grid((cols, rows): (i32, i32), add_cols: i32, add_rows: i32) -> (i32, i32) {
+    let resized = (
+        (cols + add_cols).clamp(1, GRID_MAX),
+        (rows + add_rows).clamp(1, GRID_MAX),
+    );
+    if resized.0 * resized.1 < 2 {
+        return (cols, rows);
+    }
+    resized
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CompareMode {
+    /// every pane runs a different generator
+    Generators,
+    /// every pane runs the same generator with a different seed
+    Seeds,
+    /// every pane runs the same generator & seed, with parameters edited per pane
+    Params,
+}
+
+impl CompareMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            CompareMode::Generators => "generators",
+            CompareMode::Seeds => "seeds",
+            CompareMode::Params => "params",
+        }
+    }
+}
+
+pub struct Pane {
+    pub generator: Generator,
+    pub seed: u64,
+    pub map: Map,
+    metrics: MapMetrics,
+}
+
+impl Pane {
+    fn new(generator: Generator, seed: u64) -> Self {
+        let map = generator.generate_seeded(seed);
+        Self {
+            generator,
+            seed,
+            map,
+            metrics: MapMetrics::from_map(&map),
+        }
+    }
+
+    pub fn regenerate(&mut self) {
+        *self = Pane::new(self.generator, self.seed);
+    }
+}
+
+/// Comparison splits the window into a grid of panes sharing one camera
+pub struct Comparison {
+    pub mode: CompareMode,
+    pub panes: Vec<Pane>,
+    pub selected: usize,
+    cols: i32,
+    rows: i32,
+}
+
+impl Comparison {
+    pub fn new(
+        mode: CompareMode,
+        (cols, rows): (i32, i32),
+        generators: &[Generator],
+        active: usize,
+        seed: u64,
+    ) -> Self {
+        let panes = (0..cols * rows)
+            .map(|i| {
+                let i = i as usize;
+                match mode {
+                    CompareMode::Generators => {
+                        Pane::new(generators[i % generators.len()], seed + i as u64)
+                    }
+                    CompareMode::Seeds => Pane::new(generators[active], seed + i as u64),
+                    CompareMode::Params => Pane::new(generators[active], seed),
+                }
+            })
+            .collect();
+        Self {
+            mode,
+            panes,
+            selected: 0,
+            cols,
+            rows,
+        }
+    }
+
+    fn pane_rect(&self, idx: usize) -> Rect {
+        let w = screen_width() / self.cols as f32;
+        let h = screen_height() / self.rows as f32;
+        let col = idx as i32 % self.cols;
+        let row = idx as i32 / self.cols;
+        Rect::new(col as f32 * w, row as f32 * h, w, h)
+    }
+
+    pub fn pane_at(&self, pos: Vec2) -> Option<usize> {
+        (0..self.panes.len()).find(|&i| self.pane_rect(i).contains(pos))
+    }
+
+    /// draw renders every pane with the shared camera, centered on the same map point & scale
+    pub fn draw(&self, theme: &Theme, autotiling: bool, view: &MapCamera, font: TextParams) {
+        for (idx, pane) in self.panes.iter().enumerate() {
+            let r = self.pane_rect(idx);
+            set_camera(&view.pane_camera(r));
+            render_map(theme, &pane.map, autotiling, view.pane_tiles(r));
+        }
+        set_default_camera();
+
+        let bg = Color::from_rgba(40, 40, 40, 200);
+        for (idx, pane) in self.panes.iter().enumerate() {
+            let r = self.pane_rect(idx);
+            let mut label = font;
+            if idx == self.selected {
+                label.color = YELLOW;
+            }
+            let m = &pane.metrics;
+            let rows = [
+                format!("{} #{}", pane.generator.name(), pane.seed),
+                format!(
+                    "floor {:.2}  rooms {}  loops {}  dead ends {}",
+                    m.floor_ratio, m.rooms, m.loops, m.dead_ends
+                ),
+            ];
+            draw_rectangle(r.x + 4.0, r.y + 4.0, 380.0, 44.0, bg);
+            for (i, row) in rows.iter().enumerate() {
+                draw_text_ex(row, r.x + 10.0, r.y + 21.0 + i as f32 * 19.0, label);
+            }
+            draw_rectangle_lines(r.x, r.y, r.w, r.h, 2.0, label.color);
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use super::camera::MapCamera;
+use macroquad::prelude::*;
+use mapgen::fundamentals::TILESIZE;
+use mapgen::maptools::{flood_fill, Map, TileType};
+use mapgen::utils::{in_bounds, line};
+
+const MAX_UNDO: usize = 100;
+const BRUSH_SIZE_MAX: i32 = 9;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Tool {
+    Brush,
+    Rectangle,
+    Line,
+    Fill,
+    Eyedropper,
+}
+
+impl Tool {
+    fn next(self) -> Self {
+        match self {
+            Tool::Brush => Tool::Rectangle,
+            Tool::Rectangle => Tool::Line,
+            Tool::Line => Tool::Fill,
+            Tool::Fill => Tool::Eyedropper,
+            Tool::Eyedropper => Tool::Brush,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Tool::Brush => "brush",
+            Tool::Rectangle => "rectangle",
+            Tool::Line => "line",
+            Tool::Fill => "flood fill",
+            Tool::Eyedropper => "eyedropper",
+        }
+    }
+}
+
+fn tile_name(t: TileType) -> &'static str {
+    match t {
+        TileType::Wall => "wall",
+        TileType::Floor => "floor",
+        TileType::Hero => "hero",
+        TileType::Door => "door",
+    }
+}
+
+fn tile_from_value(v: i32) -> TileType {
+    match v {
+        0 => TileType::Wall,
+        1 => TileType::Floor,
+        3 => TileType::Door,
+        _ => TileType::Hero,
+    }
+}
+
+/// Editor paints TileTypes on the map with the mouse, keeping an undo/redo history
+pub struct Editor {
+    pub tool: Tool,
+    pub tile: TileType,
+    pub brush_size: i32,
+    /// tile where the current mouse stroke started, and the last painted tile
+    stroke: Option<((i32, i32), (i32, i32))>,
+    undo: Vec<Map>,
+    redo: Vec<Map>,
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        Self {
+            tool: Tool::Brush,
+            tile: TileType::Floor,
+            brush_size: 1,
+            stroke: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    /// snapshot stores the map state before a change, call it before any edit
+    pub fn snapshot(&mut self, m: &Map) {
+        self.undo.push(*m);
+        if self.undo.len() > MAX_UNDO {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    /// clear forgets the undo/redo history, for when a different map replaces the edited one
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    pub fn undo(&mut self, m: &mut Map) {
+        if let Some(prev) = self.undo.pop() {
+            self.redo.push(*m);
+            *m = prev;
+        }
+    }
+
+    pub fn redo(&mut self, m: &mut Map) {
+        if let Some(next) = self.redo.pop() {
+            self.undo.push(*m);
+            *m = next;
+        }
+    }
+
+    pub fn status(&self) -> String {
+        match self.tool {
+            Tool::Brush | Tool::Line => format!(
+                "edit: {} ({}) - {}",
+                self.tool.name(),
+                self.brush_size,
+                tile_name(self.tile)
+            ),
+            _ => format!("edit: {} - {}", self.tool.name(), tile_name(self.tile)),
+        }
+    }
+
+    pub fn handle_keys(&mut self) {
+        if is_key_pressed(KeyCode::Tab) {
+            self.tool = self.tool.next();
+        }
+        if is_key_pressed(KeyCode::Q) {
+            self.tile = match self.tile {
+                TileType::Wall => TileType::Floor,
+                TileType::Floor => TileType::Door,
+                TileType::Door => TileType::Hero,
+                TileType::Hero => TileType::Wall,
+            };
+        }
+        if is_key_pressed(KeyCode::LeftBracket) {
+            self.brush_size = (self.brush_size - 1).max(1);
+        }
+        if is_key_pressed(KeyCode::RightBracket) {
+            self.brush_size = (self.brush_size + 1).min(BRUSH_SIZE_MAX);
+        }
+    }
+
+    fn mouse_tile(view: &MapCamera) -> (i32, i32) {
+        let world = view.screen_to_world(Vec2::from(mouse_position()));
+        (
+            (world.x / TILESIZE as f32).floor() as i32,
+            (world.y / TILESIZE as f32).floor() as i32,
+        )
+    }
+
+    fn paint(&self, m: &mut Map, x: i32, y: i32) {
+        let half = self.brush_size / 2;
+        for ty in y - half..y - half + self.brush_size {
+            for tx in x - half..x - half + self.brush_size {
+                if in_bounds(tx, ty) {
+                    m[ty as usize][tx as usize] = self.tile as i32;
+                }
+            }
+        }
+    }
+
+    /// update applies the active tool with the left mouse button
+    pub fn update(&mut self, m: &mut Map, view: &MapCamera) {
+        let (x, y) = Self::mouse_tile(view);
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            match self.tool {
+                Tool::Eyedropper => {
+                    if in_bounds(x, y) {
+                        self.tile = tile_from_value(m[y as usize][x as usize]);
+                    }
+                    return;
+                }
+                Tool::Fill => {
+                    self.snapshot(m);
+                    flood_fill(m, x, y, self.tile);
+                    return;
+                }
+                _ => {
+                    self.snapshot(m);
+                    self.stroke = Some(((x, y), (x, y)));
+                    if self.tool == Tool::Brush {
+                        self.paint(m, x, y);
+                    }
+                }
+            }
+        }
+
+        let (start, last) = match self.stroke {
+            Some(stroke) => stroke,
+            None => return,
+        };
+
+        if is_mouse_button_down(MouseButton::Left) {
+            if self.tool == Tool::Brush && last != (x, y) {
+                // paint along the mouse path, so fast strokes don't leave gaps
+                for (px, py) in line(last.0, last.1, x, y) {
+                    self.paint(m, px, py);
+                }
+            }
+            self.stroke = Some((start, (x, y)));
+        } else {
+            match self.tool {
+                Tool::Rectangle => {
+                    for ty in start.1.min(y)..=start.1.max(y) {
+                        for tx in start.0.min(x)..=start.0.max(x) {
+                            if in_bounds(tx, ty) {
+                                m[ty as usize][tx as usize] = self.tile as i32;
+                            }
+                        }
+                    }
+                }
+                Tool::Line => {
+                    for (px, py) in line(start.0, start.1, x, y) {
+                        self.paint(m, px, py);
+                    }
+                }
+                _ => {}
+            }
+            self.stroke = None;
+        }
+    }
+
+    /// draw_preview outlines the tiles the active tool would change, in map coordinates
+    pub fn draw_preview(&self, view: &MapCamera) {
+        let (x, y) = Self::mouse_tile(view);
+        let size = TILESIZE as f32;
+        let color = Color::from_rgba(255, 255, 255, 160);
+        match (self.tool, self.stroke) {
+            (Tool::Rectangle, Some((start, _))) => {
+                let (x1, y1) = (start.0.min(x), start.1.min(y));
+                let (x2, y2) = (start.0.max(x), start.1.max(y));
+                draw_rectangle_lines(
+                    x1 as f32 * size,
+                    y1 as f32 * size,
+                    (x2 - x1 + 1) as f32 * size,
+                    (y2 - y1 + 1) as f32 * size,
+                    2.0,
+                    color,
+                );
+            }
+            (Tool::Line, Some((start, _))) => {
+                for (px, py) in line(start.0, start.1, x, y) {
+                    draw_rectangle_lines(
+                        px as f32 * size,
+                        py as f32 * size,
+                        size,
+                        size,
+                        1.0,
+                        color,
+                    );
+                }
+            }
+            (Tool::Brush, _) | (Tool::Line, None) => {
+                let half = self.brush_size / 2;
+                let extent = self.brush_size as f32 * size;
+                let (bx, by) = ((x - half) as f32 * size, (y - half) as f32 * size);
+                draw_rectangle_lines(bx, by, extent, extent, 2.0, color);
+            }
+            _ => draw_rectangle_lines(x as f32 * size, y as f32 * size, size, size, 2.0, color),
+        }
+    }
+}
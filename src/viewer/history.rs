@@ -0,0 +1,155 @@
+use mapgen::mapfile::save_map;
+use mapgen::maptools::Map;
+use mapgen::procgen::Generator;
+use serde::Serialize;
+use std::error::Error;
+use std::fs;
+
+/// oldest entries that aren't pinned are dropped beyond this
+const MAX_HISTORY: usize = 500;
+
+/// timestamped names an output file in the working directory after the current time
+pub fn timestamped(extension: &str) -> String {
+    let millis = (macroquad::miniquad::date::now() * 1000.0) as u64;
+    format!("mapgen_{}.{}", millis, extension)
+}
+
+/// Entry is a map along with the generator & seed that made it,
+/// generator is None for maps that were randomized, cleared or loaded
+#[derive(Copy, Clone, Debug)]
+pub struct Entry {
+    pub generator: Option<Generator>,
+    pub seed: u64,
+    pub map: Map,
+    /// pinned entries are never dropped from the history
+    pub pinned: bool,
+}
+
+/// what gets exported next to the map file, enough to generate the map again
+#[derive(Serialize)]
+struct ExportedEntry {
+    generator: Option<Generator>,
+    seed: u64,
+}
+
+/// History keeps the recent maps, so they can be stepped through & recovered
+pub struct History {
+    entries: Vec<Entry>,
+    current: usize,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            current: 0,
+        }
+    }
+
+    /// push adds a new map to the end of the history & makes it the current entry
+    pub fn push(&mut self, generator: Option<Generator>, seed: u64, map: Map) {
+        self.entries.push(Entry {
+            generator,
+            seed,
+            map,
+            pinned: false,
+        });
+        if self.entries.len() > MAX_HISTORY {
+            // toggle_pin leaves an older entry unpinned, the new one is never dropped
+            let older = &self.entries[..self.entries.len() - 1];
+            if let Some(oldest) = older.iter().position(|e| !e.pinned) {
+                self.entries.remove(oldest);
+            }
+        }
+        self.current = self.entries.len() - 1;
+    }
+
+    /// replace_current swaps the current entry's generator & map, used while tweaking parameters.
+    /// Pinned entries are kept, the new map is pushed instead.
+    pub fn replace_current(&mut self, generator: Option<Generator>, seed: u64, map: Map) {
+        match self.entries.get_mut(self.current) {
+            Some(entry) if !entry.pinned => {
+                entry.generator = generator;
+                entry.seed = seed;
+                entry.map = map;
+            }
+            _ => self.push(generator, seed, map),
+        }
+    }
+
+    /// sync stores edits made to the current map, called before leaving or replacing its entry
+    pub fn sync(&mut self, map: &Map) {
+        if let Some(entry) = self.entries.get_mut(self.current) {
+            entry.map = *map;
+        }
+    }
+
+    pub fn back(&mut self, map: &Map) -> Option<Entry> {
+        if self.current == 0 || self.entries.is_empty() {
+            return None;
+        }
+        self.sync(map);
+        self.current -= 1;
+        Some(self.entries[self.current])
+    }
+
+    pub fn forward(&mut self, map: &Map) -> Option<Entry> {
+        if self.current + 1 >= self.entries.len() {
+            return None;
+        }
+        self.sync(map);
+        self.current += 1;
+        Some(self.entries[self.current])
+    }
+
+    /// toggle_pin pins or unpins the current entry, returns None if the history is empty
+    /// or no more entries can be pinned
+    pub fn toggle_pin(&mut self, map: &Map) -> Option<bool> {
+        self.sync(map);
+        let pinned = self.entries.iter().filter(|e| e.pinned).count();
+        let entry = self.entries.get_mut(self.current)?;
+        // one entry besides the newest stays unpinned, so there is always one to drop
+        if !entry.pinned && pinned >= MAX_HISTORY - 1 {
+            return None;
+        }
+        entry.pinned = !entry.pinned;
+        Some(entry.pinned)
+    }
+
+    /// export saves the current map as a map file, and its generator & seed as RON next to it,
+    /// returns the path of the map file
+    pub fn export(&mut self, map: &Map) -> Result<String, Box<dyn Error>> {
+        self.sync(map);
+        let entry = self.entries.get(self.current).ok_or("history is empty")?;
+        let map_path = timestamped("txt");
+        save_map(&map_path, &entry.map)?;
+
+        let exported = ExportedEntry {
+            generator: entry.generator,
+            seed: entry.seed,
+        };
+        let pretty = ron::ser::PrettyConfig::default();
+        let params_path = map_path.replace(".txt", ".ron");
+        fs::write(params_path, ron::ser::to_string_pretty(&exported, pretty)?)?;
+        Ok(map_path)
+    }
+
+    pub fn status(&self) -> String {
+        let entry = match self.entries.get(self.current) {
+            Some(entry) => entry,
+            None => return "history is empty".to_owned(),
+        };
+        let source = match entry.generator {
+            Some(g) => format!("{} #{}", g.name(), entry.seed),
+            None => "no generator".to_owned(),
+        };
+        let pinned = if entry.pinned { " - pinned" } else { "" };
+        format!(
+            "history {}/{}: {}{}",
+            self.current + 1,
+            self.entries.len(),
+            source,
+            pinned
+        )
+    }
+}
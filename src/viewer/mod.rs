@@ -0,0 +1,10 @@
+pub mod camera;
+// screenshots & GIFs need file writes & a background thread, which the web build has neither of
+#[cfg(not(target_arch = "wasm32"))]
+pub mod capture;
+pub mod compare;
+pub mod editor;
+pub mod history;
+pub mod overlay;
+pub mod panel;
+pub mod theme;
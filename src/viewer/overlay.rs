@@ -0,0 +1,163 @@
+use super::camera::MapCamera;
+use macroquad::color::hsl_to_rgb;
+use macroquad::prelude::*;
+use mapgen::fundamentals::TILESIZE;
+use mapgen::maptools::{distance_map, label_regions, Map, Rect};
+use mapgen::procgen::room_placement::ConnectionPointLocation;
+use mapgen::procgen::DebugInfo;
+
+const PARTITION_COLORS: [Color; 4] = [YELLOW, ORANGE, PINK, SKYBLUE];
+
+/// Overlays draws generator debug info & map analysis on top of the map, each one toggleable
+pub struct Overlays {
+    pub partitions: bool,
+    /// room rectangles & the planned connections between them
+    pub rooms: bool,
+    pub connection_points: bool,
+    pub regions: bool,
+    /// walking distance heatmap from the tile under the cursor
+    pub distances: bool,
+}
+
+fn tile_rect(r: &Rect) -> (f32, f32, f32, f32) {
+    let size = TILESIZE as f32;
+    (
+        r.x as f32 * size,
+        r.y as f32 * size,
+        r.w as f32 * size,
+        r.h as f32 * size,
+    )
+}
+
+/// region_color spreads the hues of consecutive labels by the golden ratio, so neighbors differ
+fn region_color(label: i32) -> Color {
+    let hue = (label as f32 * 0.618_034).fract();
+    let mut color = hsl_to_rgb(hue, 0.8, 0.5);
+    color.a = 0.45;
+    color
+}
+
+/// heat_color goes from blue (close) to red (far)
+fn heat_color(t: f32) -> Color {
+    let mut color = hsl_to_rgb((1.0 - t) * 0.66, 0.9, 0.5);
+    color.a = 0.55;
+    color
+}
+
+impl Overlays {
+    pub fn new() -> Self {
+        Self {
+            partitions: false,
+            rooms: false,
+            connection_points: false,
+            regions: false,
+            distances: false,
+        }
+    }
+
+    /// handle_keys toggles overlays with F1-F5, returns a message describing the change
+    pub fn handle_keys(&mut self) -> Option<String> {
+        let toggles: [(KeyCode, &str, &mut bool); 5] = [
+            (KeyCode::F1, "BSP partitions", &mut self.partitions),
+            (KeyCode::F2, "rooms", &mut self.rooms),
+            (
+                KeyCode::F3,
+                "connection points",
+                &mut self.connection_points,
+            ),
+            (KeyCode::F4, "regions", &mut self.regions),
+            (KeyCode::F5, "distance heatmap", &mut self.distances),
+        ];
+        let mut message = None;
+        for (key, name, enabled) in toggles {
+            if is_key_pressed(key) {
+                *enabled = !*enabled;
+                let state = if *enabled { "on" } else { "off" };
+                message = Some(format!("overlay {}: {}", name, state));
+            }
+        }
+        message
+    }
+
+    /// draw renders the enabled overlays, call it with the map camera set
+    pub fn draw(&self, m: &Map, debug: &DebugInfo, view: &MapCamera) {
+        let size = TILESIZE as f32;
+        let (cols, rows) = view.visible_tiles();
+
+        if self.regions {
+            let (labels, _) = label_regions(m);
+            for y in rows.clone() {
+                for x in cols.clone() {
+                    let label = labels[y as usize][x as usize];
+                    if label != 0 {
+                        let (px, py) = (x as f32 * size, y as f32 * size);
+                        draw_rectangle(px, py, size, size, region_color(label));
+                    }
+                }
+            }
+        }
+
+        if self.distances {
+            let mouse = view.screen_to_world(Vec2::from(mouse_position()));
+            let (mx, my) = ((mouse.x / size).floor(), (mouse.y / size).floor());
+            let dist = distance_map(m, mx as i32, my as i32);
+            let max = dist
+                .iter()
+                .flat_map(|r| r.iter())
+                .max()
+                .copied()
+                .unwrap_or(0);
+            for y in rows {
+                for x in cols.clone() {
+                    let d = dist[y as usize][x as usize];
+                    if d >= 0 && max > 0 {
+                        let (px, py) = (x as f32 * size, y as f32 * size);
+                        draw_rectangle(px, py, size, size, heat_color(d as f32 / max as f32));
+                    }
+                }
+            }
+        }
+
+        if self.partitions {
+            for (r, depth) in &debug.partitions {
+                let (x, y, w, h) = tile_rect(r);
+                let color = PARTITION_COLORS[*depth as usize % PARTITION_COLORS.len()];
+                draw_rectangle_lines(x, y, w, h, 2.0, color);
+            }
+        }
+
+        if self.rooms {
+            for r in &debug.rooms {
+                let (x, y, w, h) = tile_rect(r);
+                draw_rectangle(x, y, w, h, Color::new(0.2, 0.6, 1.0, 0.25));
+                draw_rectangle_lines(x, y, w, h, 2.0, Color::new(0.2, 0.6, 1.0, 0.9));
+            }
+            for &((x1, y1), (x2, y2)) in &debug.connections {
+                let center = |v: i32| (v as f32 + 0.5) * size;
+                let (x1, y1, x2, y2) = (center(x1), center(y1), center(x2), center(y2));
+                draw_line(x1, y1, x2, y2, 2.0, Color::new(1.0, 1.0, 1.0, 0.7));
+            }
+        }
+
+        if self.connection_points {
+            for cp in &debug.connection_points {
+                let center = vec2((cp.x as f32 + 0.5) * size, (cp.y as f32 + 0.5) * size);
+                // triangle pointing out of the room, towards where the corridor goes
+                let dir = match cp.loc {
+                    ConnectionPointLocation::Top => vec2(0.0, -1.0),
+                    ConnectionPointLocation::Right => vec2(1.0, 0.0),
+                    ConnectionPointLocation::Bottom => vec2(0.0, 1.0),
+                    ConnectionPointLocation::Left => vec2(-1.0, 0.0),
+                };
+                let side = vec2(-dir.y, dir.x);
+                let color = if cp.used { GRAY } else { GREEN };
+                draw_triangle(
+                    center + dir * size * 0.45,
+                    center - dir * size * 0.3 + side * size * 0.35,
+                    center - dir * size * 0.3 - side * size * 0.35,
+                    color,
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use macroquad::prelude::*;
+use macroquad::ui::{hash, root_ui, Ui};
+use mapgen::connections::{ConnectionParams, ConnectionStrategy};
+use mapgen::corridor::{CorridorParams, CorridorStyle};
+use mapgen::maze::{CellSelection, MazeAlgorithm};
+use mapgen::procgen::bsp_tree::{SiblingConnection, SplitPosition, SplitStrategy};
+use mapgen::procgen::cellular_automata::{
+    CaPhase, CaRule, CaveSelection, Neighborhood, MAX_PHASES, MAX_RADIUS,
+};
+use mapgen::procgen::rwalk::{WalkBias, WalkOrigin};
+use mapgen::procgen::Generator;
+use std::ops::RangeInclusive;
+
+const PANEL_W: f32 = 320.0;
+const PANEL_H: f32 = 360.0;
+
+fn slider(ui: &mut Ui, label: &str, range: RangeInclusive<i32>, value: &mut i32) {
+    let mut v = *value as f32;
+    ui.slider(
+        hash!(label),
+        label,
+        *range.start() as f32..*range.end() as f32,
+        &mut v,
+    );
+    *value = v.round() as i32;
+}
+
+/// choice shows a combo box for an enum parameter, given its variants & their names
+fn choice<T: Copy + PartialEq>(ui: &mut Ui, label: &str, variants: &[(&str, T)], value: &mut T) {
+    let names: Vec<&str> = variants.iter().map(|(name, _)| *name).collect();
+    let mut idx = variants.iter().position(|(_, v)| v == value).unwrap_or(0);
+    ui.combo_box(hash!(label), label, &names, &mut idx);
+    *value = variants[idx].1;
+}
+
+fn connection_widgets(ui: &mut Ui, connections: &mut ConnectionParams) {
+    choice(
+        ui,
+        "room graph",
+        &[
+            ("chain", ConnectionStrategy::Chain),
+            ("spanning tree", ConnectionStrategy::SpanningTree),
+            ("delaunay", ConnectionStrategy::Delaunay),
+        ],
+        &mut connections.strategy,
+    );
+    if connections.strategy == ConnectionStrategy::SpanningTree {
+        slider(ui, "extra edges %", 0..=100, &mut connections.extra_edges);
+    }
+}
+
+fn corridor_widgets(ui: &mut Ui, corridor: &mut CorridorParams) {
+    choice(
+        ui,
+        "corridors",
+        &[
+            ("L-shaped", CorridorStyle::LShaped),
+            ("straight", CorridorStyle::Straight),
+            ("drunken", CorridorStyle::Drunken),
+            ("A*", CorridorStyle::AStar),
+        ],
+        &mut corridor.style,
+    );
+    slider(ui, "corridor width", 1..=4, &mut corridor.width);
+}
+
+/// phase_widgets edits a phase as a threshold rule, rules with gaps in their counts
+/// are only replaced once one of the widgets changes
+fn phase_widgets(ui: &mut Ui, n: usize, phase: &mut CaPhase) {
+    let label = |name: &str| format!("{} {}", n, name);
+    ui.label(None, &format!("phase {}: {}", n, phase.rule));
+    slider(ui, &label("iterations"), 0..=30, &mut phase.iterations);
+    let rule = phase.rule;
+    let mut neighborhood = rule.neighborhood;
+    choice(
+        ui,
+        &label("neighborhood"),
+        &[
+            ("moore", Neighborhood::Moore),
+            ("von neumann", Neighborhood::VonNeumann),
+        ],
+        &mut neighborhood,
+    );
+    let mut radius = rule.radius;
+    slider(ui, &label("radius"), 1..=MAX_RADIUS, &mut radius);
+    let max = CaRule {
+        neighborhood,
+        radius,
+        ..rule
+    }
+    .max_neighbors();
+    let mut birth = rule.birth.min().unwrap_or(max + 1);
+    let mut survival = rule.survival.min().unwrap_or(max + 1);
+    slider(ui, &label("born at walls"), 0..=max, &mut birth);
+    slider(ui, &label("survive at walls"), 0..=max, &mut survival);
+    if neighborhood != rule.neighborhood
+        || radius != rule.radius
+        || Some(birth) != rule.birth.min()
+        || Some(survival) != rule.survival.min()
+    {
+        phase.rule = CaRule::threshold(birth, survival, neighborhood, radius);
+    }
+}
+
+/// draw_params_panel shows the generator's parameters as widgets, returns true if any changed
+pub fn draw_params_panel(generator: &mut Generator, seed: u64) -> bool {
+    let before = *generator;
+    root_ui().window(hash!(), vec2(10.0, 60.0), vec2(PANEL_W, PANEL_H), |ui| {
+        ui.label(None, &format!("{} - seed {}", generator.name(), seed));
+        ui.separator();
+        match generator {
+            Generator::Tunneling(p) => {
+                slider(ui, "room size min", 3..=20, &mut p.room_size_min);
+                slider(ui, "room size max", 4..=30, &mut p.room_size_max);
+                slider(ui, "room attempts", 1..=500, &mut p.room_attempts);
+                slider(ui, "target rooms", 0..=50, &mut p.target_rooms);
+                slider(ui, "room padding", 0..=5, &mut p.room_padding);
+                ui.checkbox(hash!(), "allow overlaps", &mut p.allow_overlaps);
+                p.room_size_max = p.room_size_max.max(p.room_size_min + 1);
+                connection_widgets(ui, &mut p.connections);
+                corridor_widgets(ui, &mut p.corridor);
+            }
+            Generator::BSPTree(p) => {
+                slider(ui, "leaf min size", 6..=30, &mut p.leaf_min_size);
+                slider(ui, "room min size", 3..=20, &mut p.room_min_size);
+                slider(ui, "room max size", 4..=40, &mut p.room_max_size);
+                // rooms have to fit the smallest leaf, walls included
+                p.room_min_size = p.room_min_size.min(p.leaf_min_size - 2);
+                p.room_max_size = p.room_max_size.max(p.room_min_size + 1);
+                choice(
+                    ui,
+                    "split strategy",
+                    &[
+                        ("aspect", SplitStrategy::Aspect),
+                        ("random", SplitStrategy::Random),
+                        ("alternating", SplitStrategy::Alternating),
+                    ],
+                    &mut p.split_strategy,
+                );
+                slider(ui, "split ratio %", 100..=300, &mut p.split_ratio);
+                choice(
+                    ui,
+                    "split position",
+                    &[
+                        ("uniform", SplitPosition::Uniform),
+                        ("centered", SplitPosition::Centered),
+                        ("middle", SplitPosition::Middle),
+                    ],
+                    &mut p.split_position,
+                );
+                slider(ui, "max depth", 1..=16, &mut p.max_depth);
+                slider(ui, "empty leaf %", 0..=100, &mut p.empty_leaf_chance);
+                slider(ui, "filled leaf %", 0..=100, &mut p.filled_leaf_chance);
+                choice(
+                    ui,
+                    "connect",
+                    &[
+                        ("centers", SiblingConnection::Centers),
+                        ("nearest edges", SiblingConnection::NearestEdges),
+                        ("planned", SiblingConnection::Planned),
+                    ],
+                    &mut p.connection,
+                );
+                if p.connection == SiblingConnection::Planned {
+                    connection_widgets(ui, &mut p.connections);
+                }
+                corridor_widgets(ui, &mut p.corridor);
+            }
+            Generator::RandomWalk(p) => {
+                slider(ui, "max walkers", 1..=50, &mut p.max_walkers);
+                slider(ui, "max steps", 10..=1000, &mut p.max_steps);
+                slider(ui, "spawn chance", 0..=100, &mut p.spawn_chance);
+                slider(ui, "target floor %", 0..=90, &mut p.target_floor);
+                slider(ui, "momentum %", 0..=100, &mut p.momentum);
+                choice(
+                    ui,
+                    "bias",
+                    &[
+                        ("none", WalkBias::None),
+                        ("horizontal", WalkBias::Horizontal),
+                        ("vertical", WalkBias::Vertical),
+                        ("center", WalkBias::Center),
+                    ],
+                    &mut p.bias,
+                );
+                if p.bias != WalkBias::None {
+                    slider(ui, "bias strength %", 0..=100, &mut p.bias_strength);
+                }
+                ui.checkbox(hash!(), "diagonal steps", &mut p.diagonal);
+                slider(ui, "brush size", 1..=5, &mut p.brush_size);
+                slider(ui, "origins", 1..=10, &mut p.origins);
+                choice(
+                    ui,
+                    "origin",
+                    &[
+                        ("center", WalkOrigin::Center),
+                        ("random", WalkOrigin::Random),
+                    ],
+                    &mut p.origin,
+                );
+                ui.checkbox(hash!(), "spawn from floor", &mut p.spawn_from_floor);
+            }
+            Generator::CellularAutomata(p) => {
+                slider(ui, "wall fill %", 0..=100, &mut p.wall_fill);
+                choice(
+                    ui,
+                    "caves",
+                    &[
+                        ("random", CaveSelection::Random),
+                        ("largest", CaveSelection::Largest),
+                        ("joined", CaveSelection::Joined),
+                    ],
+                    &mut p.selection,
+                );
+                slider(ui, "min floor %", 0..=100, &mut p.min_floor);
+                slider(ui, "max floor %", 0..=100, &mut p.max_floor);
+                p.max_floor = p.max_floor.max(p.min_floor);
+                slider(ui, "max attempts", 1..=500, &mut p.max_attempts);
+                let mut phases = p.phases.count() as i32;
+                slider(ui, "phases", 1..=MAX_PHASES as i32, &mut phases);
+                p.phases.set_count(phases as usize);
+                for (i, phase) in p.phases.as_mut_slice().iter_mut().enumerate() {
+                    phase_widgets(ui, i + 1, phase);
+                }
+            }
+            Generator::RoomPlacement(p) => {
+                slider(ui, "max attempts", 1..=500, &mut p.max_attempts);
+                slider(ui, "room tries", 1..=10, &mut p.room_tries);
+                slider(ui, "min rooms", 0..=40, &mut p.min_rooms);
+                slider(ui, "max rooms", 1..=100, &mut p.max_rooms);
+                p.max_rooms = p.max_rooms.max(p.min_rooms);
+                slider(ui, "max restarts", 0..=50, &mut p.max_restarts);
+                ui.checkbox(hash!(), "prefer free space", &mut p.prefer_free_space);
+                slider(ui, "corridor min", 1..=12, &mut p.corridor_length_min);
+                slider(ui, "corridor max", 1..=12, &mut p.corridor_length_max);
+                p.corridor_length_max = p.corridor_length_max.max(p.corridor_length_min);
+                slider(ui, "bent corridor %", 0..=100, &mut p.bent_corridor_chance);
+                slider(ui, "door %", 0..=100, &mut p.door_chance);
+                ui.label(None, "room shape weights");
+                let shapes = &mut p.shapes;
+                for (name, shape) in [
+                    ("square", &mut shapes.square),
+                    ("rectangle", &mut shapes.rectangle),
+                    ("cross", &mut shapes.cross),
+                    ("circle", &mut shapes.circle),
+                    ("cave", &mut shapes.cave),
+                    ("L-shaped", &mut shapes.l_shaped),
+                    ("ring", &mut shapes.ring),
+                    ("pillared", &mut shapes.pillared),
+                    ("octagon", &mut shapes.octagon),
+                    ("polygon", &mut shapes.polygon),
+                    ("alcoves", &mut shapes.alcoves),
+                ] {
+                    slider(ui, name, 0..=10, &mut shape.weight);
+                }
+            }
+            Generator::Maze(p) => {
+                choice(
+                    ui,
+                    "maze",
+                    &[
+                        ("backtracker", MazeAlgorithm::Backtracker),
+                        ("prim", MazeAlgorithm::Prim),
+                        ("kruskal", MazeAlgorithm::Kruskal),
+                        ("eller", MazeAlgorithm::Eller),
+                        (
+                            "growing tree (newest)",
+                            MazeAlgorithm::GrowingTree(CellSelection::Newest),
+                        ),
+                        (
+                            "growing tree (random)",
+                            MazeAlgorithm::GrowingTree(CellSelection::Random),
+                        ),
+                        (
+                            "growing tree (mixed)",
+                            MazeAlgorithm::GrowingTree(CellSelection::Mixed),
+                        ),
+                        ("wilson", MazeAlgorithm::Wilson),
+                        ("binary tree", MazeAlgorithm::BinaryTree),
+                        ("hunt and kill", MazeAlgorithm::HuntAndKill),
+                    ],
+                    &mut p.algorithm,
+                );
+                ui.checkbox(hash!(), "align to grid", &mut p.align_to_grid);
+                slider(ui, "corridor width", 1..=4, &mut p.corridor_width);
+                slider(ui, "room attempts", 0..=100, &mut p.room_attempts);
+                slider(ui, "dead end passes", 0..=20, &mut p.dead_end_passes);
+                slider(
+                    ui,
+                    "extra connectors %",
+                    0..=100,
+                    &mut p.extra_connector_chance,
+                );
+                let mut linked = p.room_connections.is_some();
+                ui.checkbox(hash!(), "link rooms directly", &mut linked);
+                match (linked, p.room_connections.as_mut()) {
+                    (true, Some(connections)) => connection_widgets(ui, connections),
+                    (true, None) => p.room_connections = Some(ConnectionParams::default()),
+                    (false, _) => p.room_connections = None,
+                }
+            }
+        }
+    });
+    *generator != before
+}
+
+/// mouse_over_panel tells if the mouse is over a UI window, so the map should ignore it
+pub fn mouse_over_panel() -> bool {
+    root_ui().is_mouse_over(Vec2::from(mouse_position()))
+}
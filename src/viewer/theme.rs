@@ -0,0 +1,292 @@
+use macroquad::file::FileError;
+use macroquad::prelude::*;
+use mapgen::autotile::{wall_bitmask, AutotileRules};
+use mapgen::fundamentals::TILESIZE;
+use mapgen::maptools::{Map, TileType};
+use ron::error::SpannedError;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+fn default_fps() -> f32 {
+    4.0
+}
+
+/// SpriteDef is a sprite as stored in a theme file: one or more frames, given as
+/// (column, row) cells of the theme's tile size in one of the theme's textures
+#[derive(Deserialize)]
+struct SpriteDef {
+    #[serde(default)]
+    texture: usize,
+    frames: Vec<(i32, i32)>,
+    #[serde(default = "default_fps")]
+    fps: f32,
+    #[serde(default)]
+    tint: Option<(u8, u8, u8, u8)>,
+}
+
+#[derive(Deserialize)]
+struct ThemeDef {
+    name: String,
+    textures: Vec<String>,
+    tile_size: i32,
+    tiles: HashMap<TileType, SpriteDef>,
+    /// path to the wall autotiling rules, walls are drawn with the Wall sprite if missing
+    #[serde(default)]
+    autotile: Option<String>,
+    /// sprites of the autotile variants named in the rules
+    #[serde(default)]
+    walls: HashMap<String, SpriteDef>,
+}
+
+impl SpriteDef {
+    fn validate(&self, name: &str, num_textures: usize) -> Result<(), ThemeError> {
+        if self.texture >= num_textures {
+            return Err(ThemeError::MissingTexture {
+                sprite: name.to_string(),
+                texture: self.texture,
+            });
+        }
+        if self.frames.is_empty() {
+            return Err(ThemeError::NoFrames(name.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// ThemeError is a theme file that can't be loaded, or a sprite of it that can't be drawn
+#[derive(Debug)]
+pub enum ThemeError {
+    Io(FileError),
+    Parse { path: String, error: SpannedError },
+    Texture(FileError),
+    MissingTexture { sprite: String, texture: usize },
+    NoFrames(String),
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ThemeError::Io(e) => write!(f, "{}", e),
+            ThemeError::Parse { path, error } => write!(f, "parsing {} failed: {}", path, error),
+            ThemeError::Texture(e) => write!(f, "texture: {}", e),
+            ThemeError::MissingTexture { sprite, texture } => {
+                write!(f, "sprite {} uses missing texture {}", sprite, texture)
+            }
+            ThemeError::NoFrames(sprite) => write!(f, "sprite {} has no frames", sprite),
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+/// Sprite is an animated tile image, or a plain colored tile without a texture
+struct Sprite {
+    texture: Option<Texture2D>,
+    frames: Vec<Rect>,
+    fps: f32,
+    tint: Color,
+}
+
+impl Sprite {
+    fn new(def: SpriteDef, textures: &[Texture2D], tile_size: i32) -> Self {
+        let size = tile_size as f32;
+        Self {
+            texture: Some(textures[def.texture]),
+            frames: def
+                .frames
+                .iter()
+                .map(|&(col, row)| Rect::new(col as f32 * size, row as f32 * size, size, size))
+                .collect(),
+            fps: def.fps,
+            tint: match def.tint {
+                Some((r, g, b, a)) => Color::from_rgba(r, g, b, a),
+                None => WHITE,
+            },
+        }
+    }
+
+    fn solid(color: Color) -> Self {
+        Self {
+            texture: None,
+            frames: Vec::new(),
+            fps: 0.0,
+            tint: color,
+        }
+    }
+
+    fn draw(&self, x: f32, y: f32) {
+        let texture = match self.texture {
+            Some(texture) => texture,
+            None => {
+                let size = TILESIZE as f32;
+                draw_rectangle(x, y, size, size, self.tint);
+                return;
+            }
+        };
+        let frame = (get_time() * self.fps as f64) as usize % self.frames.len();
+        draw_texture_ex(
+            texture,
+            x,
+            y,
+            self.tint,
+            DrawTextureParams {
+                source: Some(self.frames[frame]),
+                dest_size: Some(vec2(TILESIZE as f32, TILESIZE as f32)),
+                ..Default::default()
+            },
+        );
+    }
+}
+
+/// Theme is a loaded tileset: sprites for every TileType and optionally autotiled walls
+pub struct Theme {
+    pub name: String,
+    pub autotile: Option<AutotileRules>,
+    tiles: HashMap<TileType, Sprite>,
+    walls: HashMap<String, Sprite>,
+}
+
+impl Theme {
+    /// load reads a theme file, failing on sprites that point past the textures or have no frames
+    pub async fn load(path: &str) -> Result<Self, ThemeError> {
+        let def: ThemeDef = parse(path).await?;
+        for (tile, sprite) in &def.tiles {
+            sprite.validate(&format!("{:?}", tile), def.textures.len())?;
+        }
+        for (variant, sprite) in &def.walls {
+            sprite.validate(variant, def.textures.len())?;
+        }
+
+        let mut textures: Vec<Texture2D> = Vec::new();
+        for texture_path in &def.textures {
+            let texture = load_texture(texture_path)
+                .await
+                .map_err(ThemeError::Texture)?;
+            texture.set_filter(FilterMode::Nearest);
+            textures.push(texture);
+        }
+
+        let autotile = match &def.autotile {
+            Some(rules_path) => Some(parse(rules_path).await?),
+            None => None,
+        };
+
+        let tile_size = def.tile_size;
+        Ok(Self {
+            name: def.name,
+            autotile,
+            tiles: def
+                .tiles
+                .into_iter()
+                .map(|(t, s)| (t, Sprite::new(s, &textures, tile_size)))
+                .collect(),
+            walls: def
+                .walls
+                .into_iter()
+                .map(|(v, s)| (v, Sprite::new(s, &textures, tile_size)))
+                .collect(),
+        })
+    }
+
+    /// builtin is a plain colored theme, used when no theme file could be loaded
+    pub fn builtin() -> Self {
+        Self {
+            name: "builtin".to_owned(),
+            autotile: None,
+            tiles: [
+                (TileType::Wall, DARKGRAY),
+                (TileType::Floor, LIGHTGRAY),
+                (TileType::Hero, YELLOW),
+                (TileType::Door, BROWN),
+            ]
+            .into_iter()
+            .map(|(t, color)| (t, Sprite::solid(color)))
+            .collect(),
+            walls: HashMap::new(),
+        }
+    }
+
+    pub fn draw_tile(&self, tile: TileType, x: f32, y: f32) {
+        if let Some(sprite) = self.tiles.get(&tile) {
+            sprite.draw(x, y);
+        }
+    }
+
+    /// draw_wall draws an autotile variant, returns false if the theme has no sprite for it
+    pub fn draw_wall(&self, variant: &str, x: f32, y: f32) -> bool {
+        match self.walls.get(variant) {
+            Some(sprite) => {
+                sprite.draw(x, y);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// parse reads a RON file, used for theme files, theme lists & autotile rules
+async fn parse<T: DeserializeOwned>(path: &str) -> Result<T, ThemeError> {
+    let s = load_string(path).await.map_err(ThemeError::Io)?;
+    ron::from_str(&s).map_err(|error| ThemeError::Parse {
+        path: path.to_owned(),
+        error,
+    })
+}
+
+/// load_themes loads every theme listed in the given theme list file, skipping broken ones.
+/// The builtin theme is used if none loaded.
+pub async fn load_themes(path: &str) -> Vec<Theme> {
+    let paths: Vec<String> = match parse(path).await {
+        Ok(paths) => paths,
+        Err(e) => {
+            warn!("theme list failed to load: {}", e);
+            Vec::new()
+        }
+    };
+    let mut themes: Vec<Theme> = Vec::new();
+    for theme_path in paths {
+        match Theme::load(&theme_path).await {
+            Ok(theme) => themes.push(theme),
+            Err(e) => warn!("theme {} skipped: {}", theme_path, e),
+        }
+    }
+    if themes.is_empty() {
+        themes.push(Theme::builtin());
+    }
+    themes
+}
+
+/// render_map draws the given column & row ranges of the map
+pub fn render_map(
+    theme: &Theme,
+    m: &Map,
+    autotiling: bool,
+    (cols, rows): (Range<i32>, Range<i32>),
+) {
+    let rules = theme.autotile.as_ref().filter(|_| autotiling);
+    for row in rows {
+        for col in cols.clone() {
+            let x = col as f32 * TILESIZE as f32;
+            let y = row as f32 * TILESIZE as f32;
+
+            let curr_tile = m[row as usize][col as usize];
+            let curr_type = match curr_tile {
+                0 => TileType::Wall,
+                1 => TileType::Floor,
+                3 => TileType::Door,
+                _ => TileType::Hero,
+            };
+
+            if let (Some(rules), TileType::Wall) = (rules, curr_type) {
+                let variant = rules.variant(wall_bitmask(m, col, row, rules.neighborhood));
+                if theme.draw_wall(variant, x, y) {
+                    continue;
+                }
+            }
+            theme.draw_tile(curr_type, x, y);
+        }
+    }
+}